@@ -1,4 +1,4 @@
-use math::{mat4::*, vec3::*};
+use math::{angle::*, mat4::*, vec3::*};
 
 pub struct OrbitCamera {
     pub target: Vec3,
@@ -6,19 +6,19 @@ pub struct OrbitCamera {
     pub yaw: f32,
     pub pitch: f32,
 
-    pub fov_y: f32,
+    pub fov_y: Rad,
     pub znear: f32,
     pub zfar: f32,
 }
 
 impl OrbitCamera {
-    pub fn new(target: Vec3, distance: f32, fov_y: f32, znear: f32, zfar: f32) -> Self {
+    pub fn new(target: Vec3, distance: f32, fov_y: impl Into<Rad>, znear: f32, zfar: f32) -> Self {
         OrbitCamera {
             target,
             distance,
             yaw: 0.0,
             pitch: 0.0,
-            fov_y,
+            fov_y: fov_y.into(),
             znear,
             zfar,
         }
@@ -5,7 +5,28 @@
 #include <stdlib.h>
 #define STB_TRUETYPE_IMPLEMENTATION  // force following include to generate implementation
 #include "stb_truetype.h"
+// Status codes returned by initialize, initialize_headless and
+// create_font_data; mirrored in the Rust bindings.
+#define ENGINE_OK 0
+#define ENGINE_GLFW_INIT_FAILED 1
+#define ENGINE_WINDOW_FAILED 2
+#define ENGINE_GL_LOAD_FAILED 3
+#define ENGINE_FRAMEBUFFER_INCOMPLETE 4
+#define ENGINE_FONT_NOT_FOUND 5
+#define ENGINE_FONT_INVALID 6
+#define ENGINE_FONT_ATLAS_TOO_SMALL 7
+
 GLFWwindow* window;
+// Description of the last GLFW error, for the Rust side to report.
+char last_error[1024];
+// Offscreen framebuffer used instead of the window's in headless mode.
+int headless;
+unsigned int headless_fbo;
+unsigned int headless_color;
+unsigned int headless_depth;
+// Size of the bound render target; 0 while the default framebuffer is bound.
+int target_width;
+int target_height;
 int keydown;
 int keyup;
 int keyrepeat;
@@ -107,6 +128,18 @@ void cull_back_faces(){
     glCullFace(GL_BACK);
 }
 
+void error_callback(int code, const char* description){
+    snprintf(last_error, sizeof(last_error), "%s (0x%x)", description, code);
+}
+
+const char* get_last_error(){
+    return last_error;
+}
+
+void free_string(char* str){
+    free(str);
+}
+
 unsigned char *load_file(const char *path, size_t *out_size) {
     FILE *f = fopen(path, "rb");
     if (!f) return NULL;
@@ -128,15 +161,25 @@ struct FontData{
     stbtt_bakedchar* baked_chars;
 };
 
-struct FontData create_font_data(const char *ttf_path, float pixel_height, int atlas_width, int atlas_height) {
+// Bake the printable ASCII range into an atlas. On success out owns two
+// malloc'd arrays; on failure out is zeroed. ENGINE_FONT_ATLAS_TOO_SMALL
+// stores the number of characters that did fit in chars_baked.
+int create_font_data(const char *ttf_path, float pixel_height, int atlas_width, int atlas_height, struct FontData* out, int* chars_baked) {
     int num_chars = 96;
     size_t ttf_size;
-    unsigned char *ttf_buffer = load_file(ttf_path, &ttf_size);
     struct FontData fontdata = {0};
-
+    *out = fontdata;
+    *chars_baked = 0;
+    unsigned char *ttf_buffer = load_file(ttf_path, &ttf_size);
     if (!ttf_buffer) {
-        printf("Failed to load font: %s\n", ttf_path);
-        return fontdata;
+        return ENGINE_FONT_NOT_FOUND;
+    }
+
+    stbtt_fontinfo info;
+    int offset = stbtt_GetFontOffsetForIndex(ttf_buffer, 0);
+    if (offset < 0 || !stbtt_InitFont(&info, ttf_buffer, offset)) {
+        free(ttf_buffer);
+        return ENGINE_FONT_INVALID;
     }
 
     uint8_t* atlas_bitmap = malloc(atlas_width * atlas_height);
@@ -153,15 +196,25 @@ struct FontData create_font_data(const char *ttf_path, float pixel_height, int a
         num_chars,                    // num chars
         baked_chars            // output char info
     );
+    free(ttf_buffer);
 
     if (res <= 0) {
-        printf("stbtt_BakeFontBitmap failed\n");
+        // A negative result is the number of characters that fit.
+        *chars_baked = -res;
+        free(atlas_bitmap);
+        free(baked_chars);
+        return ENGINE_FONT_ATLAS_TOO_SMALL;
     }
 
-    free(ttf_buffer);
     fontdata.atlas_bitmap = atlas_bitmap;
     fontdata.baked_chars = baked_chars;
-    return fontdata;
+    *out = fontdata;
+    return ENGINE_OK;
+}
+
+void free_font_data(struct FontData fontdata){
+    free(fontdata.atlas_bitmap);
+    free(fontdata.baked_chars);
 }
 
 unsigned int create_texture(){
@@ -177,23 +230,29 @@ unsigned int create_texture(){
     return texture;
 }
 
-void tex_image_2d(uint8_t* ptr, int width, int height, int channels){
-    if(channels == 1){
-        glTexImage2D(GL_TEXTURE_2D, 0, GL_RGBA, width, height, 0, GL_RED, GL_UNSIGNED_BYTE, ptr);
-    }
-    else if(channels == 3){
-        glTexImage2D(GL_TEXTURE_2D, 0, GL_RGBA, width, height, 0, GL_RGB, GL_UNSIGNED_BYTE, ptr);
-    }
-    else if(channels == 4){
-        glTexImage2D(GL_TEXTURE_2D, 0, GL_RGBA, width, height, 0, GL_RGBA, GL_UNSIGNED_BYTE, ptr);
-    }
-    else{
-        printf("channels should be 1, 3 or 4");
+void tex_image_2d(uint8_t* ptr, int width, int height, int internal_format, unsigned int format, unsigned int type){
+    // Rows of 1-3 byte pixels are not 4-byte aligned.
+    glPixelStorei(GL_UNPACK_ALIGNMENT, 1);
+    glTexImage2D(GL_TEXTURE_2D, 0, internal_format, width, height, 0, format, type, ptr);
+    // Sample like the CPU side: one channel is luminance, two are
+    // luminance + alpha.
+    GLint swizzle[4] = {GL_RED, GL_GREEN, GL_BLUE, GL_ALPHA};
+    if (format == GL_RED || format == GL_RG) {
+        swizzle[1] = GL_RED;
+        swizzle[2] = GL_RED;
+        swizzle[3] = format == GL_RG ? GL_GREEN : GL_ONE;
     }
+    glTexParameteriv(GL_TEXTURE_2D, GL_TEXTURE_SWIZZLE_RGBA, swizzle);
+    // Any old mip levels no longer match; generate_mipmap_2d turns them
+    // back on.
+    glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_NEAREST);
 }
 
 void generate_mipmap_2d(){
     glGenerateMipmap(GL_TEXTURE_2D);
+    // Nearest texels within a level, blended between levels, like the CPU
+    // MipChain::sample.
+    glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_NEAREST_MIPMAP_LINEAR);
 }
 
 void viewport(int x, int y, int w, int h){
@@ -212,7 +271,31 @@ void draw_triangle_arrays(unsigned int num_vertices){
     glDrawArrays(GL_TRIANGLES, 0, num_vertices);
 }
 
+// mode is a GL primitive such as GL_LINES or GL_TRIANGLE_STRIP.
+void draw_arrays(unsigned int mode, int first, int count){
+    glDrawArrays(mode, first, count);
+}
+
+// Draw count indices of the bound element buffer, starting at byte
+// offset; type is GL_UNSIGNED_SHORT or GL_UNSIGNED_INT.
+void draw_elements(unsigned int mode, int count, unsigned int type, size_t offset){
+    glDrawElements(mode, count, type, (void*)offset);
+}
+
+void draw_arrays_instanced(unsigned int mode, int first, int count, int instances){
+    glDrawArraysInstanced(mode, first, count, instances);
+}
+
+void draw_elements_instanced(unsigned int mode, int count, unsigned int type, size_t offset, int instances){
+    glDrawElementsInstanced(mode, count, type, (void*)offset, instances);
+}
+
 void swap_buffers(){
+    if (headless) {
+        // Surfaceless contexts have nothing to present.
+        glFinish();
+        return;
+    }
     glfwSwapBuffers(window);
 }
 
@@ -228,11 +311,13 @@ void poll_events(){
     glfwPollEvents();
 }
 
-void initialize(int screenWidth, int screenHeight)
+int initialize(int screenWidth, int screenHeight)
 {
     // glfw: initialize and configure
     // ------------------------------
-    glfwInit();
+    glfwSetErrorCallback(error_callback);
+    if (!glfwInit())
+        return ENGINE_GLFW_INIT_FAILED;
     glfwWindowHint(GLFW_CONTEXT_VERSION_MAJOR, 3);
     glfwWindowHint(GLFW_CONTEXT_VERSION_MINOR, 3);
     glfwWindowHint(GLFW_OPENGL_PROFILE, GLFW_OPENGL_CORE_PROFILE);
@@ -246,9 +331,8 @@ void initialize(int screenWidth, int screenHeight)
     window = glfwCreateWindow(screenWidth, screenHeight, "LearnOpenGL", NULL, NULL);
     if (window == NULL)
     {
-        printf("Failed to create GLFW window");
         glfwTerminate();
-        return;
+        return ENGINE_WINDOW_FAILED;
     }
     glfwMakeContextCurrent(window);
 
@@ -256,8 +340,10 @@ void initialize(int screenWidth, int screenHeight)
     // ---------------------------------------
     if (!gladLoadGLLoader((GLADloadproc)glfwGetProcAddress))
     {
-        printf("Failed to initialize GLAD");
-        return;
+        glfwDestroyWindow(window);
+        glfwTerminate();
+        window = NULL;
+        return ENGINE_GL_LOAD_FAILED;
     }
     double xpos, ypos;
     glfwGetCursorPos(window, &xpos, &ypos);
@@ -269,9 +355,116 @@ void initialize(int screenWidth, int screenHeight)
     glfwSetMouseButtonCallback(window, mouse_callback);
     glfwSetCursorPosCallback(window, cursor_pos_callback);
     glfwSetCharCallback(window, char_callback);
+    return ENGINE_OK;
+}
+
+static GLFWwindow* create_headless_window(int width, int height, int context_api)
+{
+    glfwDefaultWindowHints();
+    glfwWindowHint(GLFW_CONTEXT_VERSION_MAJOR, 3);
+    glfwWindowHint(GLFW_CONTEXT_VERSION_MINOR, 3);
+    glfwWindowHint(GLFW_OPENGL_PROFILE, GLFW_OPENGL_CORE_PROFILE);
+    glfwWindowHint(GLFW_VISIBLE, GLFW_FALSE);
+    glfwWindowHint(GLFW_CONTEXT_CREATION_API, context_api);
+    return glfwCreateWindow(width, height, "cengine headless", NULL, NULL);
+}
+
+// Offscreen context for machines without a display or GPU: GLFW's null
+// platform with an OSMesa context, falling back to surfaceless EGL (e.g.
+// Mesa llvmpipe). Rendering goes to an RGBA8 + depth framebuffer object,
+// since surfaceless contexts have no default framebuffer.
+// Returns ENGINE_OK or the status of the step that failed.
+// Delete the headless framebuffer and its renderbuffers, if any. Needs
+// the context to still be current.
+static void delete_headless_framebuffer(void)
+{
+    if (headless_fbo) glDeleteFramebuffers(1, &headless_fbo);
+    if (headless_color) glDeleteRenderbuffers(1, &headless_color);
+    if (headless_depth) glDeleteRenderbuffers(1, &headless_depth);
+    headless_fbo = 0;
+    headless_color = 0;
+    headless_depth = 0;
+}
+
+int initialize_headless(int width, int height)
+{
+    glfwSetErrorCallback(error_callback);
+    glfwInitHint(GLFW_PLATFORM, GLFW_PLATFORM_NULL);
+    if (!glfwInit())
+        return ENGINE_GLFW_INIT_FAILED;
+    window = create_headless_window(width, height, GLFW_OSMESA_CONTEXT_API);
+    if (window == NULL)
+        window = create_headless_window(width, height, GLFW_EGL_CONTEXT_API);
+    if (window == NULL)
+    {
+        glfwTerminate();
+        return ENGINE_WINDOW_FAILED;
+    }
+    glfwMakeContextCurrent(window);
+    if (!gladLoadGLLoader((GLADloadproc)glfwGetProcAddress))
+    {
+        glfwDestroyWindow(window);
+        glfwTerminate();
+        window = NULL;
+        return ENGINE_GL_LOAD_FAILED;
+    }
+
+    glGenFramebuffers(1, &headless_fbo);
+    glBindFramebuffer(GL_FRAMEBUFFER, headless_fbo);
+    glGenRenderbuffers(1, &headless_color);
+    glBindRenderbuffer(GL_RENDERBUFFER, headless_color);
+    glRenderbufferStorage(GL_RENDERBUFFER, GL_RGBA8, width, height);
+    glFramebufferRenderbuffer(GL_FRAMEBUFFER, GL_COLOR_ATTACHMENT0, GL_RENDERBUFFER, headless_color);
+    glGenRenderbuffers(1, &headless_depth);
+    glBindRenderbuffer(GL_RENDERBUFFER, headless_depth);
+    glRenderbufferStorage(GL_RENDERBUFFER, GL_DEPTH24_STENCIL8, width, height);
+    glFramebufferRenderbuffer(GL_FRAMEBUFFER, GL_DEPTH_STENCIL_ATTACHMENT, GL_RENDERBUFFER, headless_depth);
+    if (glCheckFramebufferStatus(GL_FRAMEBUFFER) != GL_FRAMEBUFFER_COMPLETE)
+    {
+        glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        delete_headless_framebuffer();
+        glfwDestroyWindow(window);
+        glfwTerminate();
+        window = NULL;
+        return ENGINE_FRAMEBUFFER_INCOMPLETE;
+    }
+    glViewport(0, 0, width, height);
+    headless = 1;
+    mousepos.x = 0;
+    mousepos.y = 0;
+    mousedelta.x = 0;
+    mousedelta.y = 0;
+    return ENGINE_OK;
+}
+
+// Read RGBA8 pixels of the current framebuffer into out, bottom row first.
+void read_pixels(int x, int y, int width, int height, uint8_t* out)
+{
+    glPixelStorei(GL_PACK_ALIGNMENT, 1);
+    glReadPixels(x, y, width, height, GL_RGBA, GL_UNSIGNED_BYTE, out);
+}
+
+// Full info log of a shader or program, malloc'd; free with free_string.
+static char* get_info_log(unsigned int object, int is_program)
+{
+    int length = 0;
+    if (is_program)
+        glGetProgramiv(object, GL_INFO_LOG_LENGTH, &length);
+    else
+        glGetShaderiv(object, GL_INFO_LOG_LENGTH, &length);
+    char* log = malloc(length + 1);
+    log[0] = '\0';
+    if (length > 0) {
+        if (is_program)
+            glGetProgramInfoLog(object, length + 1, NULL, log);
+        else
+            glGetShaderInfoLog(object, length + 1, NULL, log);
+    }
+    return log;
 }
 
-unsigned int createShader(const char* source, int shaderType)
+// Returns the compiled shader, or 0 with its info log in *info_log.
+unsigned int createShader(const char* source, int shaderType, char** info_log)
 {
     unsigned int shader;
     shader = glCreateShader(shaderType);
@@ -282,44 +475,90 @@ unsigned int createShader(const char* source, int shaderType)
     glGetShaderiv(shader, GL_COMPILE_STATUS, &success);
     if(!success)
     {
-        char infoLog[512];
-        glGetShaderInfoLog(shader, 512, NULL, infoLog);
-        printf("%s\n", source);
-        printf("--- shader failed ---\n");
-        printf("%s\n", infoLog);
+        *info_log = get_info_log(shader, 0);
+        glDeleteShader(shader);
+        return 0;
     }
     return shader;
 }
 
-unsigned int initialize_program(char* vertexSource, char* fragmentSource){
-    unsigned int vertexShader = createShader(vertexSource, GL_VERTEX_SHADER);
-    unsigned int fragmentShader = createShader(fragmentSource, GL_FRAGMENT_SHADER);
+#define STAGE_NONE 0
+#define STAGE_VERTEX 1
+#define STAGE_FRAGMENT 2
+#define STAGE_LINK 3
+
+struct ProgramResult{
+    unsigned int program;
+    // STAGE_NONE on success, otherwise the stage that failed.
+    int failed_stage;
+    // Info log of the failed stage, NULL on success; free with free_string.
+    char* info_log;
+};
+
+struct ProgramResult initialize_program(char* vertexSource, char* fragmentSource){
+    struct ProgramResult result = {0, STAGE_NONE, NULL};
+    unsigned int vertexShader = createShader(vertexSource, GL_VERTEX_SHADER, &result.info_log);
+    if (!vertexShader) {
+        result.failed_stage = STAGE_VERTEX;
+        return result;
+    }
+    unsigned int fragmentShader = createShader(fragmentSource, GL_FRAGMENT_SHADER, &result.info_log);
+    if (!fragmentShader) {
+        glDeleteShader(vertexShader);
+        result.failed_stage = STAGE_FRAGMENT;
+        return result;
+    }
     unsigned int program = glCreateProgram();
     glAttachShader(program, vertexShader);
     glAttachShader(program, fragmentShader);
     glLinkProgram(program);
+    glDeleteShader(vertexShader);
+    glDeleteShader(fragmentShader);
 
     int  success;
     glGetProgramiv(program, GL_LINK_STATUS, &success);
     if(!success) {
-        char infoLog[512];
-        glGetProgramInfoLog(program, 512, NULL, infoLog);
-        printf("--- compilation failed ---\n");
-        printf("%s\n", infoLog);
+        result.info_log = get_info_log(program, 1);
+        result.failed_stage = STAGE_LINK;
+        glDeleteProgram(program);
+        return result;
+    }
+    result.program = program;
+    return result;
+}
+
+// -1 if the program has no active uniform called name.
+int get_uniform_location(unsigned int program, const char* name){
+    return glGetUniformLocation(program, name);
+}
+
+// Uniform setters act on the bound program. Matrices are row-major.
+void set_uniform_floats(int location, int components, int count, const float* values){
+    switch (components) {
+        case 1: glUniform1fv(location, count, values); break;
+        case 2: glUniform2fv(location, count, values); break;
+        case 3: glUniform3fv(location, count, values); break;
+        case 4: glUniform4fv(location, count, values); break;
     }
-    glDeleteShader(vertexShader);
-    glDeleteShader(fragmentShader);
-    return program;
 }
 
-void set_matrix4(unsigned int program, char* name, float* ptr){
-    unsigned int loc = glGetUniformLocation(program, name);
-    glUniformMatrix4fv(loc, 1, GL_TRUE, ptr);
+void set_uniform_ints(int location, int count, const int* values){
+    glUniform1iv(location, count, values);
 }
 
-void set_vector3(unsigned int program, char* name, float x, float y, float z){
-    unsigned int loc = glGetUniformLocation(program, name);
-    glUniform3f(loc, x, y, z);
+void set_uniform_matrix3(int location, int count, const float* values){
+    glUniformMatrix3fv(location, count, GL_TRUE, values);
+}
+
+void set_uniform_matrix4(int location, int count, const float* values){
+    glUniformMatrix4fv(location, count, GL_TRUE, values);
+}
+
+// Bind texture to a texture unit for samplers, leaving unit 0 active.
+void bind_texture_unit(unsigned int unit, unsigned int texture){
+    glActiveTexture(GL_TEXTURE0 + unit);
+    glBindTexture(GL_TEXTURE_2D, texture);
+    glActiveTexture(GL_TEXTURE0);
 }
 
 void bind_program(unsigned int program){
@@ -334,6 +573,11 @@ void bind_vbo(unsigned int vbo){
     glBindBuffer(GL_ARRAY_BUFFER, vbo);
 }
 
+// The element buffer binding is part of the bound vertex array.
+void bind_ebo(unsigned int ebo){
+    glBindBuffer(GL_ELEMENT_ARRAY_BUFFER, ebo);
+}
+
 void bind_texture(unsigned int texture){
     glBindTexture(GL_TEXTURE_2D, texture);
 }
@@ -346,11 +590,24 @@ void update_vertices_dynamic(uint8_t* ptr, unsigned int size){
     glBufferData(GL_ARRAY_BUFFER, size, ptr, GL_DYNAMIC_DRAW);
 }
 
+void update_indices_static(uint8_t* ptr, unsigned int size){
+    glBufferData(GL_ELEMENT_ARRAY_BUFFER, size, ptr, GL_STATIC_DRAW);
+}
+
+void update_indices_dynamic(uint8_t* ptr, unsigned int size){
+    glBufferData(GL_ELEMENT_ARRAY_BUFFER, size, ptr, GL_DYNAMIC_DRAW);
+}
+
 void vertex_attrib_pointer_float(unsigned int id, int count, uint stride, size_t ptr){
     glVertexAttribPointer(id, count, GL_FLOAT, GL_FALSE, stride, (void*)ptr);
     glEnableVertexAttribArray(id);
 }
 
+// Advance attribute id once every divisor instances instead of per vertex.
+void vertex_attrib_divisor(unsigned int id, unsigned int divisor){
+    glVertexAttribDivisor(id, divisor);
+}
+
 unsigned int create_vao(){
     unsigned int VAO;
     glGenVertexArrays(1, &VAO);
@@ -373,7 +630,70 @@ void enable_transparency(){
 }
 
 void terminate(){
+    if (window) {
+        glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        delete_headless_framebuffer();
+    }
     glfwTerminate();
+    // Deletes after this point have no context to act on and are skipped.
+    window = NULL;
+    headless = 0;
+    target_width = 0;
+    target_height = 0;
+}
+
+void delete_program(unsigned int program){
+    if (window) glDeleteProgram(program);
+}
+
+void delete_vao(unsigned int vao){
+    if (window) glDeleteVertexArrays(1, &vao);
+}
+
+void delete_vbo(unsigned int vbo){
+    if (window) glDeleteBuffers(1, &vbo);
+}
+
+void delete_texture(unsigned int texture){
+    if (window) glDeleteTextures(1, &texture);
+}
+
+unsigned int create_framebuffer(){
+    unsigned int fbo;
+    glGenFramebuffers(1, &fbo);
+    return fbo;
+}
+
+void delete_framebuffer(unsigned int fbo){
+    if (window) glDeleteFramebuffers(1, &fbo);
+}
+
+// 0 binds the default framebuffer: the window's, or the offscreen one of a
+// headless context. width and height are the size of fbo's attachments.
+void bind_framebuffer(unsigned int fbo, int width, int height){
+    glBindFramebuffer(GL_FRAMEBUFFER, fbo ? fbo : (headless ? headless_fbo : 0));
+    target_width = fbo ? width : 0;
+    target_height = fbo ? height : 0;
+}
+
+// Attach texture to the bound framebuffer as color attachment index, or as
+// the depth attachment when index is -1.
+void framebuffer_texture(int index, unsigned int texture){
+    GLenum attachment = index < 0 ? GL_DEPTH_ATTACHMENT : GL_COLOR_ATTACHMENT0 + index;
+    glFramebufferTexture2D(GL_FRAMEBUFFER, attachment, GL_TEXTURE_2D, texture, 0);
+}
+
+// Draw into the first count color attachments of the bound framebuffer.
+void draw_buffers(int count){
+    // RenderTarget rejects more than MAX_COLOR_ATTACHMENTS (8) up front.
+    GLenum buffers[8];
+    if (count > 8) count = 8;
+    for (int i = 0; i < count; i++) buffers[i] = GL_COLOR_ATTACHMENT0 + i;
+    glDrawBuffers(count, buffers);
+}
+
+int framebuffer_complete(){
+    return glCheckFramebufferStatus(GL_FRAMEBUFFER) == GL_FRAMEBUFFER_COMPLETE;
 }
 
 struct Vec2i get_window_size(){
@@ -381,3 +701,10 @@ struct Vec2i get_window_size(){
     glfwGetFramebufferSize(window, &window_size.x, &window_size.y);
     return window_size;
 }
+
+// Size of the bound render target, or of the window.
+struct Vec2i get_target_size(){
+    if (!target_width) return get_window_size();
+    struct Vec2i size = { target_width, target_height };
+    return size;
+}
@@ -0,0 +1,198 @@
+use std::f32::consts::{PI, TAU};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// Angle in radians.
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct Rad(pub f32);
+
+/// Angle in degrees.
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct Deg(pub f32);
+
+impl Rad {
+    pub const ZERO: Rad = Rad(0.0);
+    pub const HALF_TURN: Rad = Rad(PI);
+    pub const FULL_TURN: Rad = Rad(TAU);
+
+    #[inline]
+    pub fn to_deg(self) -> Deg {
+        Deg(self.0.to_degrees())
+    }
+
+    #[inline]
+    pub fn sin(self) -> f32 {
+        self.0.sin()
+    }
+
+    #[inline]
+    pub fn cos(self) -> f32 {
+        self.0.cos()
+    }
+
+    #[inline]
+    pub fn tan(self) -> f32 {
+        self.0.tan()
+    }
+
+    #[inline]
+    pub fn sin_cos(self) -> (f32, f32) {
+        self.0.sin_cos()
+    }
+
+    /// Wrap into the range [0, 2π).
+    pub fn normalized(self) -> Rad {
+        let r = self.0.rem_euclid(TAU);
+        // rem_euclid can round up to exactly TAU for tiny negative inputs.
+        if r >= TAU { Rad(0.0) } else { Rad(r) }
+    }
+
+    /// Wrap into the range [-π, π).
+    pub fn wrapped(self) -> Rad {
+        Rad((self + Rad(PI)).normalized().0 - PI)
+    }
+
+    /// Shortest signed angle that rotates `self` onto `other`, in [-π, π).
+    pub fn difference(self, other: Rad) -> Rad {
+        (other - self).wrapped()
+    }
+
+    /// Interpolate along the shortest arc between `self` and `other`.
+    pub fn lerp(self, other: Rad, t: f32) -> Rad {
+        self + self.difference(other) * t
+    }
+}
+
+impl Deg {
+    pub const ZERO: Deg = Deg(0.0);
+    pub const HALF_TURN: Deg = Deg(180.0);
+    pub const FULL_TURN: Deg = Deg(360.0);
+
+    #[inline]
+    pub fn to_rad(self) -> Rad {
+        Rad(self.0.to_radians())
+    }
+
+    #[inline]
+    pub fn sin(self) -> f32 {
+        self.to_rad().sin()
+    }
+
+    #[inline]
+    pub fn cos(self) -> f32 {
+        self.to_rad().cos()
+    }
+
+    #[inline]
+    pub fn tan(self) -> f32 {
+        self.to_rad().tan()
+    }
+
+    #[inline]
+    pub fn sin_cos(self) -> (f32, f32) {
+        self.to_rad().sin_cos()
+    }
+
+    /// Wrap into the range [0, 360).
+    pub fn normalized(self) -> Deg {
+        let d = self.0.rem_euclid(360.0);
+        if d >= 360.0 { Deg(0.0) } else { Deg(d) }
+    }
+
+    /// Wrap into the range [-180, 180).
+    pub fn wrapped(self) -> Deg {
+        Deg((self + Deg(180.0)).normalized().0 - 180.0)
+    }
+
+    /// Shortest signed angle that rotates `self` onto `other`, in [-180, 180).
+    pub fn difference(self, other: Deg) -> Deg {
+        (other - self).wrapped()
+    }
+
+    /// Interpolate along the shortest arc between `self` and `other`.
+    pub fn lerp(self, other: Deg, t: f32) -> Deg {
+        self + self.difference(other) * t
+    }
+}
+
+impl From<Deg> for Rad {
+    #[inline]
+    fn from(d: Deg) -> Rad {
+        d.to_rad()
+    }
+}
+
+impl From<Rad> for Deg {
+    #[inline]
+    fn from(r: Rad) -> Deg {
+        r.to_deg()
+    }
+}
+
+// A bare f32 is treated as radians, matching the existing APIs.
+impl From<f32> for Rad {
+    #[inline]
+    fn from(r: f32) -> Rad {
+        Rad(r)
+    }
+}
+
+macro_rules! impl_angle_ops {
+    ($t:ident) => {
+        impl Add for $t {
+            type Output = $t;
+
+            #[inline]
+            fn add(self, rhs: $t) -> $t {
+                $t(self.0 + rhs.0)
+            }
+        }
+
+        impl Sub for $t {
+            type Output = $t;
+
+            #[inline]
+            fn sub(self, rhs: $t) -> $t {
+                $t(self.0 - rhs.0)
+            }
+        }
+
+        impl Neg for $t {
+            type Output = $t;
+
+            #[inline]
+            fn neg(self) -> $t {
+                $t(-self.0)
+            }
+        }
+
+        impl Mul<f32> for $t {
+            type Output = $t;
+
+            #[inline]
+            fn mul(self, rhs: f32) -> $t {
+                $t(self.0 * rhs)
+            }
+        }
+
+        impl Mul<$t> for f32 {
+            type Output = $t;
+
+            #[inline]
+            fn mul(self, rhs: $t) -> $t {
+                $t(self * rhs.0)
+            }
+        }
+
+        impl Div<f32> for $t {
+            type Output = $t;
+
+            #[inline]
+            fn div(self, rhs: f32) -> $t {
+                $t(self.0 / rhs)
+            }
+        }
+    };
+}
+
+impl_angle_ops!(Rad);
+impl_angle_ops!(Deg);
@@ -0,0 +1,187 @@
+use crate::{mat4::*, quat::*, vec3::*};
+use std::ops::Mul;
+
+/// Unit dual quaternion representing a rigid transform (rotation + translation).
+///
+/// `real` holds the rotation and `dual` encodes the translation as
+/// `dual = 0.5 * t * real`, where `t` is the pure quaternion (0, t).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DualQuat {
+    pub real: Quat,
+    pub dual: Quat,
+}
+
+impl DualQuat {
+    pub const IDENTITY: DualQuat = DualQuat {
+        real: Quat::IDENTITY,
+        dual: Quat::new(0.0, 0.0, 0.0, 0.0),
+    };
+
+    #[inline]
+    pub const fn new(real: Quat, dual: Quat) -> Self {
+        DualQuat { real, dual }
+    }
+
+    /// Rigid transform that rotates by `rotation` and then translates by `translation`.
+    pub fn from_rotation_translation(rotation: Quat, translation: Vec3) -> Self {
+        let real = rotation.normalized();
+        let t = Quat::new(0.0, translation.x, translation.y, translation.z);
+        DualQuat {
+            real,
+            dual: (t * real) * 0.5,
+        }
+    }
+
+    pub fn from_rotation(rotation: Quat) -> Self {
+        Self::from_rotation_translation(rotation, Vec3::ZERO)
+    }
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self::from_rotation_translation(Quat::IDENTITY, translation)
+    }
+
+    /// Build from a rigid transform matrix. Scale and shear are discarded.
+    pub fn from_mat4(m: Mat4) -> Self {
+        let translation = Vec3::new(m.m[3], m.m[7], m.m[11]);
+        Self::from_rotation_translation(Quat::from_mat4(m), translation)
+    }
+
+    pub fn to_mat4(self) -> Mat4 {
+        Mat4::translate(self.translation()) * self.rotation().to_mat4()
+    }
+
+    pub fn rotation(self) -> Quat {
+        self.real.normalized()
+    }
+
+    pub fn translation(self) -> Vec3 {
+        let t = (self.dual * 2.0) * self.real.conjugate();
+        Vec3::new(t.x, t.y, t.z)
+    }
+
+    /// Make the real part unit length and the dual part orthogonal to it.
+    pub fn normalized(self) -> Self {
+        let len = self.real.length();
+        if len == 0.0 {
+            return self;
+        }
+        let real = self.real * (1.0 / len);
+        let dual = self.dual * (1.0 / len);
+        DualQuat {
+            real,
+            dual: dual + real * -real.dot(dual),
+        }
+    }
+
+    /// Conjugate of both parts; the inverse for unit dual quaternions.
+    pub fn conjugate(self) -> Self {
+        DualQuat {
+            real: self.real.conjugate(),
+            dual: self.dual.conjugate(),
+        }
+    }
+
+    /// Inverse rigid transform.
+    pub fn inverse(self) -> Self {
+        self.normalized().conjugate()
+    }
+
+    /// Composition: the result applies `rhs` first, then `self` (like `Mat4 * Mat4`).
+    pub fn compose(self, rhs: DualQuat) -> Self {
+        DualQuat {
+            real: self.real * rhs.real,
+            dual: self.real * rhs.dual + self.dual * rhs.real,
+        }
+    }
+
+    pub fn transform_point3(self, p: Vec3) -> Vec3 {
+        self.real.rotate_vec3(p) + self.translation()
+    }
+
+    pub fn transform_dir3(self, v: Vec3) -> Vec3 {
+        self.real.rotate_vec3(v)
+    }
+
+    /// Screw linear interpolation: constant-speed motion along the screw axis
+    /// between `self` (t = 0) and `other` (t = 1).
+    pub fn sclerp(self, other: DualQuat, t: f32) -> Self {
+        let a = self.normalized();
+        let mut b = other.normalized();
+        // Take the shortest path.
+        if a.real.dot(b.real) < 0.0 {
+            b = DualQuat::new(-b.real, -b.dual);
+        }
+        let diff = a.conjugate().compose(b);
+        a.compose(diff.pow(t)).normalized()
+    }
+
+    /// Raise a unit dual quaternion to a scalar power via its screw parameters.
+    fn pow(self, t: f32) -> Self {
+        let r = self.real;
+        let d = self.dual;
+        let vr = Vec3::new(r.x, r.y, r.z);
+        let vd = Vec3::new(d.x, d.y, d.z);
+        let len = vr.length();
+
+        // Pure translation: scale it linearly.
+        if len < 1e-6 {
+            return DualQuat::new(Quat::IDENTITY, Quat::new(0.0, vd.x * t, vd.y * t, vd.z * t));
+        }
+
+        let inv_len = 1.0 / len;
+        let angle = 2.0 * r.w.clamp(-1.0, 1.0).acos();
+        let pitch = -2.0 * d.w * inv_len;
+        let direction = vr * inv_len;
+        let moment = (vd - direction * (pitch * r.w * 0.5)) * inv_len;
+
+        let angle = angle * t;
+        let pitch = pitch * t;
+        let (sin_a, cos_a) = (angle * 0.5).sin_cos();
+
+        let real_v = direction * sin_a;
+        let dual_v = moment * sin_a + direction * (pitch * 0.5 * cos_a);
+        DualQuat::new(
+            Quat::new(cos_a, real_v.x, real_v.y, real_v.z),
+            Quat::new(-pitch * 0.5 * sin_a, dual_v.x, dual_v.y, dual_v.z),
+        )
+    }
+
+    /// Dual quaternion linear blending (DLB) of weighted transforms,
+    /// as used for skinning. Returns identity if `items` is empty.
+    pub fn blend(items: &[(DualQuat, f32)]) -> Self {
+        let Some(&(pivot, _)) = items.first() else {
+            return DualQuat::IDENTITY;
+        };
+        let mut real = Quat::new(0.0, 0.0, 0.0, 0.0);
+        let mut dual = Quat::new(0.0, 0.0, 0.0, 0.0);
+        for &(dq, weight) in items {
+            // Keep every rotation in the same hemisphere as the first one.
+            let w = if dq.real.dot(pivot.real) < 0.0 {
+                -weight
+            } else {
+                weight
+            };
+            real = real + dq.real * w;
+            dual = dual + dq.dual * w;
+        }
+        DualQuat::new(real, dual).normalized()
+    }
+}
+
+// DualQuat * DualQuat
+impl Mul<DualQuat> for DualQuat {
+    type Output = DualQuat;
+
+    fn mul(self, rhs: DualQuat) -> DualQuat {
+        self.compose(rhs)
+    }
+}
+
+// DualQuat * Vec3 -> position transform
+impl Mul<Vec3> for DualQuat {
+    type Output = Vec3;
+
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        self.transform_point3(rhs)
+    }
+}
@@ -1,6 +1,9 @@
+pub mod angle;
+pub mod dual_quat;
 pub mod mat4;
 pub mod quat;
 pub mod rect;
+pub mod rect_pack;
 pub mod texture;
 pub mod vec3;
 use crate::{mat4::Mat4, vec3::*};
@@ -41,6 +44,7 @@ impl Triangle3 {
 }
 
 #[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Vec2i {
     pub x: i32,
     pub y: i32,
@@ -72,6 +76,7 @@ impl Add for Vec2 {
     }
 }
 
+#[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub struct Color {
     pub r: f32,
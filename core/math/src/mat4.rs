@@ -1,10 +1,11 @@
 use crate::*;
-use crate::{quat::*, vec3::*};
+use crate::{angle::*, quat::*, vec3::*};
 use std::ops::Mul;
 
 /// 4×4 matrix in row-major order.
 ///
 /// m[row][col] = m[row * 4 + col]
+#[repr(C)]
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Mat4 {
     pub m: [f32; 16],
@@ -43,8 +44,8 @@ impl Mat4 {
         }
     }
 
-    pub fn perspective(fov_y: f32, aspect: f32, znear: f32, zfar: f32) -> Self {
-        let f = 1.0 / (fov_y * 0.5).tan();
+    pub fn perspective(fov_y: impl Into<Rad>, aspect: f32, znear: f32, zfar: f32) -> Self {
+        let f = 1.0 / (fov_y.into() * 0.5).tan();
         let a = f / aspect;
         let b = (zfar + znear) / (znear - zfar);
         let c = (2.0 * zfar * znear) / (znear - zfar);
@@ -105,7 +106,7 @@ impl Mat4 {
         Mat4::translate(t) * r.to_mat4() * Mat4::scale(s)
     }
 
-    pub fn trs2d(t: Vec2, r: f32, s: Vec2) -> Mat4 {
+    pub fn trs2d(t: Vec2, r: impl Into<Rad>, s: Vec2) -> Mat4 {
         Mat4::translate(t.to_vec3()) * Mat4::rotate_z(r) * Mat4::scale(s.to_vec3())
     }
 
@@ -123,6 +124,41 @@ impl Mat4 {
         }
     }
 
+    /// Transform a Vec3 as a position without the perspective divide,
+    /// returning the homogeneous (x, y, z, w) result.
+    pub fn transform_point4(self, v: Vec3) -> [f32; 4] {
+        let row = |r: usize| {
+            self.m[r * 4] * v.x
+                + self.m[r * 4 + 1] * v.y
+                + self.m[r * 4 + 2] * v.z
+                + self.m[r * 4 + 3]
+        };
+        [row(0), row(1), row(2), row(3)]
+    }
+
+    /// Inverse transpose of the upper 3×3, for transforming normals with
+    /// `transform_dir3`. Singular matrices return the identity.
+    pub fn normal_matrix(self) -> Mat4 {
+        let a = |r: usize, c: usize| self.m[r * 4 + c];
+        // Cofactors of the 3×3; the inverse transpose is cofactor / det.
+        let cof = |r: usize, c: usize| {
+            let (r0, r1) = ((r + 1) % 3, (r + 2) % 3);
+            let (c0, c1) = ((c + 1) % 3, (c + 2) % 3);
+            a(r0, c0) * a(r1, c1) - a(r0, c1) * a(r1, c0)
+        };
+        let det = a(0, 0) * cof(0, 0) + a(0, 1) * cof(0, 1) + a(0, 2) * cof(0, 2);
+        if det == 0.0 {
+            return Mat4::IDENTITY;
+        }
+        let mut out = Mat4::IDENTITY;
+        for r in 0..3 {
+            for c in 0..3 {
+                out.m[r * 4 + c] = cof(r, c) / det;
+            }
+        }
+        out
+    }
+
     /// Transform a Vec3 as a direction (ignores translation).
     pub fn transform_dir3(self, v: Vec3) -> Vec3 {
         let x = self.m[0] * v.x + self.m[1] * v.y + self.m[2] * v.z;
@@ -156,27 +192,24 @@ impl Mat4 {
         )
     }
 
-    pub fn rotate_x(angle: f32) -> Self {
-        let c = angle.cos();
-        let s = angle.sin();
+    pub fn rotate_x(angle: impl Into<Rad>) -> Self {
+        let (s, c) = angle.into().sin_cos();
 
         Mat4::new(
             1.0, 0.0, 0.0, 0.0, 0.0, c, -s, 0.0, 0.0, s, c, 0.0, 0.0, 0.0, 0.0, 1.0,
         )
     }
 
-    pub fn rotate_y(angle: f32) -> Self {
-        let c = angle.cos();
-        let s = angle.sin();
+    pub fn rotate_y(angle: impl Into<Rad>) -> Self {
+        let (s, c) = angle.into().sin_cos();
 
         Mat4::new(
             c, 0.0, s, 0.0, 0.0, 1.0, 0.0, 0.0, -s, 0.0, c, 0.0, 0.0, 0.0, 0.0, 1.0,
         )
     }
 
-    pub fn rotate_z(angle: f32) -> Self {
-        let c = angle.cos();
-        let s = angle.sin();
+    pub fn rotate_z(angle: impl Into<Rad>) -> Self {
+        let (s, c) = angle.into().sin_cos();
 
         Mat4::new(
             c, -s, 0.0, 0.0, s, c, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
@@ -1,5 +1,5 @@
-use crate::{mat4::*, vec3::*};
-use std::ops::Mul;
+use crate::{angle::*, mat4::*, vec3::*};
+use std::ops::{Add, Mul, Neg};
 
 /// Unit quaternion representing rotation.
 /// Stored as (w, x, y, z) with w being the scalar part.
@@ -28,15 +28,27 @@ impl Quat {
 
         // Build a 3x3 rotation matrix from the basis:
         // columns = right, up, forward
-        let m00 = r.x;
-        let m01 = u.x;
-        let m02 = f.x;
-        let m10 = r.y;
-        let m11 = u.y;
-        let m12 = f.y;
-        let m20 = r.z;
-        let m21 = u.z;
-        let m22 = f.z;
+        Self::from_rotation_basis(r, u, f)
+    }
+
+    /// Extract the rotation from the upper 3×3 of `m`.
+    /// Any scale is removed by normalizing the basis columns.
+    pub fn from_mat4(m: Mat4) -> Self {
+        let col = |c: usize| Vec3::new(m.m[c], m.m[4 + c], m.m[8 + c]).normalized();
+        Self::from_rotation_basis(col(0), col(1), col(2))
+    }
+
+    /// Convert a rotation matrix given by its columns to a quaternion.
+    fn from_rotation_basis(c0: Vec3, c1: Vec3, c2: Vec3) -> Self {
+        let m00 = c0.x;
+        let m01 = c1.x;
+        let m02 = c2.x;
+        let m10 = c0.y;
+        let m11 = c1.y;
+        let m12 = c2.y;
+        let m20 = c0.z;
+        let m21 = c1.z;
+        let m22 = c2.z;
 
         // Convert rotation matrix to quaternion
         let trace = m00 + m11 + m22;
@@ -86,8 +98,8 @@ impl Quat {
     }
 
     /// Construct from axis-angle (axis must be non-zero; will be normalized).
-    pub fn from_axis_angle(axis: Vec3, angle_rad: f32) -> Self {
-        let half = angle_rad * 0.5;
+    pub fn from_axis_angle(axis: Vec3, angle: impl Into<Rad>) -> Self {
+        let half = angle.into() * 0.5;
         let (s, c) = half.sin_cos();
         let axis = axis.normalized();
         Quat {
@@ -98,6 +110,21 @@ impl Quat {
         }
     }
 
+    /// Conjugate; the inverse rotation for unit quaternions.
+    pub fn conjugate(self) -> Self {
+        Quat {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+
+    /// 4D dot product.
+    pub fn dot(self, other: Quat) -> f32 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
     /// Quaternion magnitude.
     pub fn length(self) -> f32 {
         (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
@@ -187,3 +214,35 @@ impl Mul<Vec3> for Quat {
         self.rotate_vec3(rhs)
     }
 }
+
+// Component-wise Quat + Quat.
+impl Add<Quat> for Quat {
+    type Output = Quat;
+
+    fn add(self, rhs: Quat) -> Quat {
+        Quat::new(
+            self.w + rhs.w,
+            self.x + rhs.x,
+            self.y + rhs.y,
+            self.z + rhs.z,
+        )
+    }
+}
+
+// Quat * scalar
+impl Mul<f32> for Quat {
+    type Output = Quat;
+
+    fn mul(self, rhs: f32) -> Quat {
+        Quat::new(self.w * rhs, self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+// -Quat; represents the same rotation.
+impl Neg for Quat {
+    type Output = Quat;
+
+    fn neg(self) -> Quat {
+        Quat::new(-self.w, -self.x, -self.y, -self.z)
+    }
+}
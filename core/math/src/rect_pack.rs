@@ -0,0 +1,266 @@
+//! Rectangle bin packing for texture atlases.
+//!
+//! Both packers place axis-aligned rectangles without rotation into a fixed
+//! size bin and return the top-left corner of each placement.
+
+/// A rectangle with integer pixel coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PackRect {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+impl PackRect {
+    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+        PackRect { x, y, w, h }
+    }
+
+    fn right(&self) -> i32 {
+        self.x + self.w
+    }
+
+    fn bottom(&self) -> i32 {
+        self.y + self.h
+    }
+
+    fn contains(&self, other: &PackRect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+
+    fn intersects(&self, other: &PackRect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+}
+
+pub trait RectPacker {
+    /// Place a `w` × `h` rectangle, returning its top-left corner,
+    /// or `None` if it does not fit.
+    fn insert(&mut self, w: i32, h: i32) -> Option<(i32, i32)>;
+
+    /// Fraction of the bin area covered by placed rectangles.
+    fn occupancy(&self) -> f32;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PackAlgorithm {
+    /// Tightest packing, slower for many rectangles.
+    #[default]
+    MaxRects,
+    /// Fast, works best when rectangles have similar heights (e.g. glyphs).
+    Skyline,
+}
+
+impl PackAlgorithm {
+    pub fn packer(self, width: i32, height: i32) -> Box<dyn RectPacker> {
+        match self {
+            PackAlgorithm::MaxRects => Box::new(MaxRectsPacker::new(width, height)),
+            PackAlgorithm::Skyline => Box::new(SkylinePacker::new(width, height)),
+        }
+    }
+}
+
+/// MaxRects packer using the best short side fit heuristic.
+#[derive(Clone, Debug)]
+pub struct MaxRectsPacker {
+    width: i32,
+    height: i32,
+    used_area: i64,
+    free: Vec<PackRect>,
+}
+
+impl MaxRectsPacker {
+    pub fn new(width: i32, height: i32) -> Self {
+        MaxRectsPacker {
+            width,
+            height,
+            used_area: 0,
+            free: vec![PackRect::new(0, 0, width, height)],
+        }
+    }
+
+    /// Split every free rectangle overlapping `used` into the parts around it.
+    fn split_free(&mut self, used: &PackRect) {
+        let mut i = 0;
+        while i < self.free.len() {
+            let f = self.free[i];
+            if !f.intersects(used) {
+                i += 1;
+                continue;
+            }
+            self.free.swap_remove(i);
+            if used.x > f.x {
+                self.free.push(PackRect::new(f.x, f.y, used.x - f.x, f.h));
+            }
+            if used.right() < f.right() {
+                self.free.push(PackRect::new(
+                    used.right(),
+                    f.y,
+                    f.right() - used.right(),
+                    f.h,
+                ));
+            }
+            if used.y > f.y {
+                self.free.push(PackRect::new(f.x, f.y, f.w, used.y - f.y));
+            }
+            if used.bottom() < f.bottom() {
+                self.free.push(PackRect::new(
+                    f.x,
+                    used.bottom(),
+                    f.w,
+                    f.bottom() - used.bottom(),
+                ));
+            }
+        }
+    }
+
+    /// Drop free rectangles fully contained in another one.
+    fn prune(&mut self) {
+        let mut i = 0;
+        while i < self.free.len() {
+            let contained = (0..self.free.len()).any(|j| {
+                j != i
+                    && self.free[j].contains(&self.free[i])
+                    && (self.free[j] != self.free[i] || j < i)
+            });
+            if contained {
+                self.free.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+impl RectPacker for MaxRectsPacker {
+    fn insert(&mut self, w: i32, h: i32) -> Option<(i32, i32)> {
+        if w <= 0 || h <= 0 {
+            return None;
+        }
+        let best = self
+            .free
+            .iter()
+            .filter(|f| f.w >= w && f.h >= h)
+            .min_by_key(|f| {
+                let (dw, dh) = (f.w - w, f.h - h);
+                (dw.min(dh), dw.max(dh))
+            })
+            .copied()?;
+        let used = PackRect::new(best.x, best.y, w, h);
+        self.split_free(&used);
+        self.prune();
+        self.used_area += w as i64 * h as i64;
+        Some((used.x, used.y))
+    }
+
+    fn occupancy(&self) -> f32 {
+        self.used_area as f32 / (self.width as f32 * self.height as f32)
+    }
+}
+
+/// One horizontal segment of the skyline: the bin is filled up to `y`
+/// over [x, x + w).
+#[derive(Clone, Copy, Debug)]
+struct SkylineNode {
+    x: i32,
+    y: i32,
+    w: i32,
+}
+
+/// Skyline packer using the bottom-left heuristic.
+#[derive(Clone, Debug)]
+pub struct SkylinePacker {
+    width: i32,
+    height: i32,
+    used_area: i64,
+    nodes: Vec<SkylineNode>,
+}
+
+impl SkylinePacker {
+    pub fn new(width: i32, height: i32) -> Self {
+        SkylinePacker {
+            width,
+            height,
+            used_area: 0,
+            nodes: vec![SkylineNode {
+                x: 0,
+                y: 0,
+                w: width,
+            }],
+        }
+    }
+
+    /// Height at which a `w` wide rectangle would rest when starting at node `i`.
+    fn fit(&self, i: usize, w: i32, h: i32) -> Option<i32> {
+        let x = self.nodes[i].x;
+        if x + w > self.width {
+            return None;
+        }
+        let mut y = 0;
+        let mut remaining = w;
+        for node in &self.nodes[i..] {
+            if remaining <= 0 {
+                break;
+            }
+            y = y.max(node.y);
+            remaining -= node.w;
+        }
+        (y + h <= self.height).then_some(y)
+    }
+}
+
+impl RectPacker for SkylinePacker {
+    fn insert(&mut self, w: i32, h: i32) -> Option<(i32, i32)> {
+        if w <= 0 || h <= 0 {
+            return None;
+        }
+        // Lowest top edge wins, ties go to the narrower supporting segment.
+        let (best, y) = (0..self.nodes.len())
+            .filter_map(|i| self.fit(i, w, h).map(|y| (i, y)))
+            .min_by_key(|&(i, y)| (y + h, self.nodes[i].w))?;
+        let x = self.nodes[best].x;
+
+        let node = SkylineNode { x, y: y + h, w };
+        self.nodes.insert(best, node);
+        // Trim the segments now covered by the new node.
+        let i = best + 1;
+        while i < self.nodes.len() {
+            let end = node.x + node.w;
+            let next = &mut self.nodes[i];
+            if next.x >= end {
+                break;
+            }
+            let overlap = end - next.x;
+            if overlap >= next.w {
+                self.nodes.remove(i);
+            } else {
+                next.x += overlap;
+                next.w -= overlap;
+                break;
+            }
+        }
+        // Merge neighbours at the same height.
+        let mut j = 0;
+        while j + 1 < self.nodes.len() {
+            if self.nodes[j].y == self.nodes[j + 1].y {
+                self.nodes[j].w += self.nodes[j + 1].w;
+                self.nodes.remove(j + 1);
+            } else {
+                j += 1;
+            }
+        }
+        self.used_area += w as i64 * h as i64;
+        Some((x, y))
+    }
+
+    fn occupancy(&self) -> f32 {
+        self.used_area as f32 / (self.width as f32 * self.height as f32)
+    }
+}
@@ -1,25 +1,173 @@
+pub mod atlas;
+pub mod blend;
+pub mod diff;
+mod draw;
+pub mod filter;
+mod float_image;
+mod heightmap;
+pub mod mip;
+pub mod pixel_format;
+pub mod png;
+pub mod ppm;
+pub mod quantize;
+pub mod raster;
+pub mod resample;
+pub mod sdf;
+pub mod tga;
+mod zlib;
+
+use std::fmt;
+use std::path::Path;
+
+pub use pixel_format::PixelFormat;
+
+/// Error produced when loading, saving or encoding a texture.
 #[derive(Debug)]
+pub enum ImageError {
+    Io(std::io::Error),
+    /// The file is not a valid image of the detected format.
+    Corrupt(String),
+    /// The file is valid but uses a feature or layout that is not supported.
+    Unsupported(String),
+    /// The texture itself cannot be encoded (e.g. its data is too short).
+    InvalidTexture(String),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Io(e) => write!(f, "io error: {}", e),
+            ImageError::Corrupt(msg) => write!(f, "corrupt image: {}", msg),
+            ImageError::Unsupported(msg) => write!(f, "unsupported image: {}", msg),
+            ImageError::InvalidTexture(msg) => write!(f, "invalid texture: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImageError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ImageError {
+    fn from(e: std::io::Error) -> Self {
+        ImageError::Io(e)
+    }
+}
+
+/// Largest decoded image, in bytes, that the decoders will allocate.
+pub(crate) const MAX_DECODED_BYTES: usize = 1 << 30;
+
+/// Size in bytes of a decoded `width` x `height` image with `channels`
+/// bytes per pixel, or `None` if a dimension is zero or the image is larger
+/// than `MAX_DECODED_BYTES`.
+pub(crate) fn decoded_size(width: usize, height: usize, channels: usize) -> Option<usize> {
+    if width == 0 || height == 0 || width > i32::MAX as usize || height > i32::MAX as usize {
+        return None;
+    }
+    width
+        .checked_mul(height)?
+        .checked_mul(channels)
+        .filter(|&size| size <= MAX_DECODED_BYTES)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Tga,
+    Ppm,
+}
+
+impl ImageFormat {
+    /// Guess the format from a file extension (case-insensitive).
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "png" => Some(ImageFormat::Png),
+            "tga" => Some(ImageFormat::Tga),
+            "ppm" | "pgm" | "pnm" => Some(ImageFormat::Ppm),
+            _ => None,
+        }
+    }
+
+    /// Guess the format from the leading bytes of a file.
+    /// TGA has no magic number, so anything unrecognized is treated as TGA.
+    pub fn from_bytes(bytes: &[u8]) -> ImageFormat {
+        if bytes.starts_with(&[0x89, b'P', b'N', b'G']) {
+            ImageFormat::Png
+        } else if bytes.len() >= 2
+            && bytes[0] == b'P'
+            && matches!(bytes[1], b'2' | b'3' | b'5' | b'6')
+        {
+            ImageFormat::Ppm
+        } else {
+            ImageFormat::Tga
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Texture {
     pub width: i32,
     pub height: i32,
-    pub channels: i32,
+    pub format: PixelFormat,
     pub data: Vec<u8>,
 }
 
 impl Texture {
-    pub fn new(width: i32, height: i32, channels: i32) -> Self {
-        let size = (width * height * channels) as usize;
+    pub fn new(width: i32, height: i32, format: PixelFormat) -> Self {
+        let size = width.max(0) as usize * height.max(0) as usize * format.bytes_per_pixel();
 
         Texture {
             width,
             height,
-            channels,
+            format,
             data: vec![0; size],
         }
     }
 
+    pub fn channels(&self) -> usize {
+        self.format.channels()
+    }
+
+    /// Load a PNG, TGA or PGM/PPM file. The format is detected from the contents.
+    pub fn load(path: impl AsRef<Path>) -> Result<Texture, ImageError> {
+        let bytes = std::fs::read(path)?;
+        Texture::decode(&bytes)
+    }
+
+    /// Save to a file, choosing the format from the extension.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ImageError> {
+        let path = path.as_ref();
+        let format = ImageFormat::from_path(path).ok_or_else(|| {
+            ImageError::Unsupported(format!("unknown image extension: {}", path.display()))
+        })?;
+        std::fs::write(path, self.encode(format)?)?;
+        Ok(())
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Texture, ImageError> {
+        match ImageFormat::from_bytes(bytes) {
+            ImageFormat::Png => png::decode(bytes),
+            ImageFormat::Tga => tga::decode(bytes),
+            ImageFormat::Ppm => ppm::decode(bytes),
+        }
+    }
+
+    pub fn encode(&self, format: ImageFormat) -> Result<Vec<u8>, ImageError> {
+        match format {
+            ImageFormat::Png => png::encode(self),
+            ImageFormat::Tga => tga::encode(self),
+            ImageFormat::Ppm => ppm::encode(self),
+        }
+    }
+
     fn index(&self, x: i32, y: i32) -> usize {
-        ((y * self.width + x) * self.channels) as usize
+        (y * self.width + x) as usize * self.format.bytes_per_pixel()
     }
 
     pub fn draw_circle(&mut self, cx: i32, cy: i32, radius: i32, color: &[u8]) {
@@ -35,14 +183,9 @@ impl Texture {
         }
     }
 
+    /// Overwrite a pixel. Out of bounds writes and colors with fewer
+    /// components than the texture are ignored; see `try_set_pixel`.
     pub fn set_pixel(&mut self, x: i32, y: i32, color: &[u8]) {
-        if x < 0 || y < 0 || x >= self.width || y >= self.height {
-            return;
-        }
-        let idx = self.index(x, y);
-
-        for c in 0..self.channels as usize {
-            self.data[idx + c] = color[c];
-        }
+        let _ = self.try_set_pixel(x, y, color);
     }
 }
@@ -0,0 +1,191 @@
+//! Packing many textures into a single atlas texture.
+
+use crate::rect::Rect;
+use crate::rect_pack::{PackAlgorithm, PackRect};
+use crate::texture::{PixelFormat, Texture};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AtlasError {
+    /// The textures do not fit in `max_size` × `max_size`.
+    TooLarge { max_size: i32 },
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtlasError::TooLarge { max_size } => {
+                write!(f, "textures do not fit in a {0}x{0} atlas", max_size)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {}
+
+/// A packed atlas. Entries are indexed by the id returned from `AtlasBuilder::add`.
+#[derive(Clone, Debug)]
+pub struct Atlas {
+    pub texture: Texture,
+    /// Pixel rectangle of each entry, excluding padding.
+    pub rects: Vec<PackRect>,
+}
+
+impl Atlas {
+    /// Normalized texture coordinates of entry `id`, as used by
+    /// `TextureRenderer::draw_texture`.
+    pub fn uv(&self, id: usize) -> Rect {
+        let r = self.rects[id];
+        let (w, h) = (self.texture.width as f32, self.texture.height as f32);
+        Rect::new(
+            r.x as f32 / w,
+            r.y as f32 / h,
+            r.w as f32 / w,
+            r.h as f32 / h,
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.rects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+}
+
+/// Collects textures and packs them into an `Atlas`.
+///
+/// The atlas starts at the smallest power of two that could hold every entry
+/// and doubles until everything fits. Padding around each entry is filled by
+/// extending its edge pixels, so bilinear sampling does not bleed neighbours.
+#[derive(Clone, Debug)]
+pub struct AtlasBuilder {
+    textures: Vec<Texture>,
+    padding: i32,
+    max_size: i32,
+    algorithm: PackAlgorithm,
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> Self {
+        AtlasBuilder::new()
+    }
+}
+
+impl AtlasBuilder {
+    pub fn new() -> Self {
+        AtlasBuilder {
+            textures: vec![],
+            padding: 1,
+            max_size: 4096,
+            algorithm: PackAlgorithm::MaxRects,
+        }
+    }
+
+    pub fn padding(mut self, padding: i32) -> Self {
+        self.padding = padding.max(0);
+        self
+    }
+
+    pub fn max_size(mut self, max_size: i32) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: PackAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Queue a texture, returning its id in the built atlas.
+    pub fn add(&mut self, texture: Texture) -> usize {
+        self.textures.push(texture);
+        self.textures.len() - 1
+    }
+
+    /// Pack every queued texture. The atlas uses the textures' format if they
+    /// all share one and `RGBA8` otherwise.
+    pub fn build(&self) -> Result<Atlas, AtlasError> {
+        let pad = self.padding;
+        let sizes: Vec<(i32, i32)> = self
+            .textures
+            .iter()
+            .map(|t| (t.width.max(0) + 2 * pad, t.height.max(0) + 2 * pad))
+            .collect();
+        // Pack tall entries first; it leaves fewer unusable gaps.
+        let mut order: Vec<usize> = (0..sizes.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse((sizes[i].1, sizes[i].0)));
+
+        let area: i64 = sizes.iter().map(|&(w, h)| w as i64 * h as i64).sum();
+        let widest = sizes.iter().map(|s| s.0).max().unwrap_or(1);
+        let tallest = sizes.iter().map(|s| s.1).max().unwrap_or(1);
+        let mut w = (widest.max(1) as u32).next_power_of_two() as i32;
+        let mut h = (tallest.max(1) as u32).next_power_of_two() as i32;
+        while (w as i64) * (h as i64) < area {
+            if w <= h {
+                w *= 2;
+            } else {
+                h *= 2;
+            }
+        }
+
+        let placed = loop {
+            if w > self.max_size || h > self.max_size {
+                return Err(AtlasError::TooLarge {
+                    max_size: self.max_size,
+                });
+            }
+            let mut packer = self.algorithm.packer(w, h);
+            let mut placed = vec![(0, 0); sizes.len()];
+            let fits = order.iter().all(|&i| {
+                let (sw, sh) = sizes[i];
+                if sw == 0 || sh == 0 {
+                    return true;
+                }
+                packer.insert(sw, sh).map(|p| placed[i] = p).is_some()
+            });
+            if fits {
+                break placed;
+            }
+            if w <= h {
+                w *= 2;
+            } else {
+                h *= 2;
+            }
+        };
+
+        let first = self.textures.first().map(|t| t.format);
+        let format = match first {
+            Some(f) if self.textures.iter().all(|t| t.format == f) => f,
+            _ => PixelFormat::RGBA8,
+        };
+        let mut texture = Texture::new(w, h, format);
+        let mut rects = Vec::with_capacity(self.textures.len());
+        for (src, &(x, y)) in self.textures.iter().zip(&placed) {
+            let rect = PackRect::new(x + pad, y + pad, src.width.max(0), src.height.max(0));
+            copy_extruded(&mut texture, &src.convert(format), rect, pad);
+            rects.push(rect);
+        }
+        Ok(Atlas { texture, rects })
+    }
+}
+
+/// Copy `src` into `dst` at `rect`, repeating its edge pixels `pad` pixels outward.
+fn copy_extruded(dst: &mut Texture, src: &Texture, rect: PackRect, pad: i32) {
+    if rect.w == 0 || rect.h == 0 {
+        return;
+    }
+    let bpp = dst.format.bytes_per_pixel();
+    for dy in -pad..rect.h + pad {
+        let sy = dy.clamp(0, rect.h - 1);
+        for dx in -pad..rect.w + pad {
+            let sx = dx.clamp(0, rect.w - 1);
+            let s = src.index(sx, sy);
+            let d = dst.index(rect.x + dx, rect.y + dy);
+            if let Some(px) = src.data.get(s..s + bpp) {
+                dst.data[d..d + bpp].copy_from_slice(px);
+            }
+        }
+    }
+}
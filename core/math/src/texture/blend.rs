@@ -0,0 +1,229 @@
+//! Blended pixel writes.
+//!
+//! Source colors given as `[u8]` are interpreted by length: 1 = gray,
+//! 2 = gray + alpha, 3 = RGB, 4 = RGBA. Destination pixels follow the
+//! texture format, which must be one of the 8-bit formats: `R8` stores
+//! luminance (`RG8` adds alpha), `RGB8` is treated as opaque and `RGBA8`
+//! stores straight (non-premultiplied) alpha, except for
+//! `BlendMode::Premultiplied`.
+
+use crate::Color;
+use crate::texture::{PixelFormat, Texture};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// Overwrite the destination with the source, ignoring alpha.
+    Replace,
+    /// Porter-Duff "source over" with straight alpha.
+    #[default]
+    Normal,
+    /// Add the alpha-weighted source to the destination.
+    Additive,
+    /// Multiply destination by source, weighted by source alpha.
+    Multiply,
+    /// Inverse multiply of the inverted colors, weighted by source alpha.
+    Screen,
+    /// "Source over" where both source and destination are premultiplied.
+    Premultiplied,
+}
+
+/// Error from the checked pixel API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelError {
+    OutOfBounds {
+        x: i32,
+        y: i32,
+    },
+    /// The source color slice has the wrong number of components.
+    ColorLength(usize),
+    /// The texture's data length does not describe a valid image.
+    InvalidTexture,
+    /// Byte colors only address 8-bit formats; use `set_pixel_f32` for the others.
+    UnsupportedFormat(PixelFormat),
+}
+
+impl fmt::Display for PixelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PixelError::OutOfBounds { x, y } => write!(f, "pixel ({}, {}) is out of bounds", x, y),
+            PixelError::ColorLength(n) => write!(f, "color has {} components, expected 1 to 4", n),
+            PixelError::InvalidTexture => write!(f, "texture layout is invalid"),
+            PixelError::UnsupportedFormat(format) => {
+                write!(f, "{:?} pixels cannot be accessed as bytes", format)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PixelError {}
+
+fn luminance(rgb: [f32; 3]) -> f32 {
+    0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2]
+}
+
+/// Expand a 1-4 component color to normalized RGBA.
+fn source_rgba(color: &[u8]) -> Option<[f32; 4]> {
+    let n = |v: u8| v as f32 / 255.0;
+    match *color {
+        [g] => Some([n(g), n(g), n(g), 1.0]),
+        [g, a] => Some([n(g), n(g), n(g), n(a)]),
+        [r, g, b] => Some([n(r), n(g), n(b), 1.0]),
+        [r, g, b, a] => Some([n(r), n(g), n(b), n(a)]),
+        _ => None,
+    }
+}
+
+fn blend_channel(mode: BlendMode, d: f32, s: f32) -> f32 {
+    match mode {
+        BlendMode::Multiply => d * s,
+        BlendMode::Screen => 1.0 - (1.0 - d) * (1.0 - s),
+        _ => s,
+    }
+}
+
+/// Blend normalized RGBA `src` onto normalized RGBA `dst`.
+pub(crate) fn blend_rgba(mode: BlendMode, dst: [f32; 4], src: [f32; 4]) -> [f32; 4] {
+    let sa = src[3];
+    let da = dst[3];
+    match mode {
+        BlendMode::Replace => src,
+        BlendMode::Normal => {
+            let out_a = sa + da * (1.0 - sa);
+            if out_a <= 0.0 {
+                return [0.0; 4];
+            }
+            let c = |i: usize| (src[i] * sa + dst[i] * da * (1.0 - sa)) / out_a;
+            [c(0), c(1), c(2), out_a]
+        }
+        BlendMode::Premultiplied => {
+            let c = |i: usize| (src[i] + dst[i] * (1.0 - sa)).min(1.0);
+            [c(0), c(1), c(2), c(3)]
+        }
+        BlendMode::Additive => {
+            let c = |i: usize| (dst[i] + src[i] * sa).min(1.0);
+            [c(0), c(1), c(2), (da + sa).min(1.0)]
+        }
+        BlendMode::Multiply | BlendMode::Screen => {
+            let c = |i: usize| dst[i] + (blend_channel(mode, dst[i], src[i]) - dst[i]) * sa;
+            [c(0), c(1), c(2), sa + da * (1.0 - sa)]
+        }
+    }
+}
+
+/// Blend `src` into one stored pixel of 1 to 4 channels.
+fn blend_into(pixel: &mut [u8], src: [f32; 4], mode: BlendMode) {
+    let n = |v: u8| v as f32 / 255.0;
+    let q = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    match pixel.len() {
+        1 => {
+            let d = n(pixel[0]);
+            let s = luminance([src[0], src[1], src[2]]);
+            let out = blend_rgba(mode, [d, d, d, 1.0], [s, s, s, src[3]]);
+            pixel[0] = q(out[0]);
+        }
+        2 => {
+            let d = n(pixel[0]);
+            let s = luminance([src[0], src[1], src[2]]);
+            let out = blend_rgba(mode, [d, d, d, n(pixel[1])], [s, s, s, src[3]]);
+            pixel.copy_from_slice(&[q(out[0]), q(out[3])]);
+        }
+        3 => {
+            let dst = [n(pixel[0]), n(pixel[1]), n(pixel[2]), 1.0];
+            let out = blend_rgba(mode, dst, src);
+            pixel.copy_from_slice(&[q(out[0]), q(out[1]), q(out[2])]);
+        }
+        4 => {
+            let dst = [n(pixel[0]), n(pixel[1]), n(pixel[2]), n(pixel[3])];
+            let out = blend_rgba(mode, dst, src);
+            pixel.copy_from_slice(&[q(out[0]), q(out[1]), q(out[2]), q(out[3])]);
+        }
+        _ => {}
+    }
+}
+
+impl Color {
+    /// Quantize to 8-bit RGBA, clamping each component to [0, 1].
+    pub fn to_rgba8(&self) -> [u8; 4] {
+        let q = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [q(self.r), q(self.g), q(self.b), q(self.a)]
+    }
+
+    pub fn from_rgba8(rgba: [u8; 4]) -> Color {
+        let n = |v: u8| v as f32 / 255.0;
+        Color::new(n(rgba[0]), n(rgba[1]), n(rgba[2]), n(rgba[3]))
+    }
+}
+
+impl Texture {
+    /// Byte range of pixel (x, y), or an error if it cannot be addressed.
+    fn pixel_range(&self, x: i32, y: i32) -> Result<std::ops::Range<usize>, PixelError> {
+        if !self.format.is_8bit() {
+            return Err(PixelError::UnsupportedFormat(self.format));
+        }
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return Err(PixelError::OutOfBounds { x, y });
+        }
+        let start = self.index(x, y);
+        let end = start + self.format.channels();
+        if end > self.data.len() {
+            return Err(PixelError::InvalidTexture);
+        }
+        Ok(start..end)
+    }
+
+    /// The stored channels of pixel (x, y), if it exists.
+    pub fn get_pixel(&self, x: i32, y: i32) -> Option<&[u8]> {
+        let range = self.pixel_range(x, y).ok()?;
+        Some(&self.data[range])
+    }
+
+    /// Checked version of `set_pixel`: copies `color` channel by channel.
+    /// `color` must have at least as many components as the texture.
+    pub fn try_set_pixel(&mut self, x: i32, y: i32, color: &[u8]) -> Result<(), PixelError> {
+        let range = self.pixel_range(x, y)?;
+        if color.len() < range.len() {
+            return Err(PixelError::ColorLength(color.len()));
+        }
+        let n = range.len();
+        self.data[range].copy_from_slice(&color[..n]);
+        Ok(())
+    }
+
+    /// Checked blended write of a 1-4 component color.
+    pub fn try_blend_pixel(
+        &mut self,
+        x: i32,
+        y: i32,
+        color: &[u8],
+        mode: BlendMode,
+    ) -> Result<(), PixelError> {
+        let src = source_rgba(color).ok_or(PixelError::ColorLength(color.len()))?;
+        let range = self.pixel_range(x, y)?;
+        blend_into(&mut self.data[range], src, mode);
+        Ok(())
+    }
+
+    /// Blended write of a 1-4 component color. Invalid writes are ignored.
+    pub fn blend_pixel(&mut self, x: i32, y: i32, color: &[u8], mode: BlendMode) {
+        let _ = self.try_blend_pixel(x, y, color, mode);
+    }
+
+    /// Blended write of a floating point color. Out of bounds writes are ignored.
+    pub fn blend_pixel_color(&mut self, x: i32, y: i32, color: Color, mode: BlendMode) {
+        self.blend_pixel(x, y, &color.to_rgba8(), mode);
+    }
+
+    /// Blend `color` over the pixel with its alpha scaled by `coverage` in [0, 1].
+    /// Used by the antialiased primitives.
+    pub(crate) fn cover_pixel(&mut self, x: i32, y: i32, color: &[u8], coverage: f32) {
+        if coverage <= 0.0 {
+            return;
+        }
+        let (Some(mut src), Ok(range)) = (source_rgba(color), self.pixel_range(x, y)) else {
+            return;
+        };
+        src[3] *= coverage.min(1.0);
+        blend_into(&mut self.data[range], src, BlendMode::Normal);
+    }
+}
@@ -0,0 +1,197 @@
+//! Image comparison for regression tests of rendered output.
+//!
+//! Pixels are compared as normalized RGBA, so textures of different formats
+//! can be compared (e.g. a float render against an 8-bit reference PNG).
+
+use crate::texture::{PixelFormat, Texture};
+use std::fmt;
+
+/// Result of `Texture::diff`. Errors are normalized to [0, 1] per channel
+/// and measured over the area the two textures share.
+#[derive(Clone, Debug)]
+pub struct DiffReport {
+    pub width: i32,
+    pub height: i32,
+    /// False when the textures have different dimensions.
+    pub same_size: bool,
+    /// Pixels with any channel beyond the tolerance, plus pixels outside
+    /// the shared area when the sizes differ.
+    pub mismatched_pixels: usize,
+    pub max_error: [f32; 4],
+    pub mean_error: [f32; 4],
+    /// Peak signal-to-noise ratio in dB; infinite for identical images.
+    pub psnr: f32,
+    /// `RGB8` visualization: the reference dimmed to gray, pixels within
+    /// tolerance but not identical in blue, and mismatches from red to
+    /// yellow with increasing error.
+    pub heatmap: Texture,
+}
+
+impl DiffReport {
+    /// True when the sizes agree and every pixel is within tolerance.
+    pub fn is_match(&self) -> bool {
+        self.same_size && self.mismatched_pixels == 0
+    }
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.same_size {
+            write!(f, "size mismatch, ")?;
+        }
+        write!(
+            f,
+            "{} of {} pixels differ, max error {:?}, mean error {:?}, PSNR {:.2} dB",
+            self.mismatched_pixels,
+            self.width.max(0) as usize * self.height.max(0) as usize,
+            self.max_error,
+            self.mean_error,
+            self.psnr
+        )
+    }
+}
+
+impl Texture {
+    /// Compare against `other` requiring an exact match.
+    pub fn diff(&self, other: &Texture) -> DiffReport {
+        self.diff_with_tolerance(other, [0.0; 4])
+    }
+
+    /// Compare against `other`, allowing each RGBA channel to differ by
+    /// up to `tolerance` (normalized, so `1.0 / 255.0` is one 8-bit step).
+    pub fn diff_with_tolerance(&self, other: &Texture, tolerance: [f32; 4]) -> DiffReport {
+        let width = self.width.max(other.width).max(0);
+        let height = self.height.max(other.height).max(0);
+        let same_size = self.width == other.width && self.height == other.height;
+        let mut heatmap = Texture::new(width, height, PixelFormat::RGB8);
+        let channels = if self.format.has_alpha() || other.format.has_alpha() {
+            4
+        } else {
+            3
+        };
+
+        let mut mismatched = 0;
+        let mut max_error = [0f32; 4];
+        let mut sum_error = [0f64; 4];
+        let mut sum_sq = 0f64;
+        let mut shared = 0usize;
+        for y in 0..height {
+            for x in 0..width {
+                let (a, b) = (self.get_pixel_f32(x, y), other.get_pixel_f32(x, y));
+                let (Some(a), Some(b)) = (a, b) else {
+                    mismatched += 1;
+                    heatmap.set_pixel(x, y, &[255, 0, 255]);
+                    continue;
+                };
+                shared += 1;
+                let mut worst = 0f32;
+                let mut over = false;
+                for c in 0..4 {
+                    let e = (a[c] - b[c]).abs();
+                    max_error[c] = max_error[c].max(e);
+                    sum_error[c] += e as f64;
+                    if c < channels {
+                        sum_sq += (e as f64).powi(2);
+                    }
+                    worst = worst.max(e);
+                    over |= e > tolerance[c];
+                }
+                let gray = 0.2126 * a[0] + 0.7152 * a[1] + 0.0722 * a[2];
+                let q = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+                let color = if over {
+                    mismatched += 1;
+                    // Red at the smallest error, yellow at full scale.
+                    [255, q(worst.sqrt()), 0]
+                } else if worst > 0.0 {
+                    [0, 0, 255]
+                } else {
+                    let g = q(gray * 0.3);
+                    [g, g, g]
+                };
+                heatmap.set_pixel(x, y, &color);
+            }
+        }
+
+        let n = shared.max(1) as f64;
+        let mse = sum_sq / (n * channels as f64);
+        let psnr = if mse == 0.0 {
+            f32::INFINITY
+        } else {
+            (10.0 * (1.0 / mse).log10()) as f32
+        };
+        DiffReport {
+            width,
+            height,
+            same_size,
+            mismatched_pixels: mismatched,
+            max_error,
+            mean_error: sum_error.map(|s| (s / n) as f32),
+            psnr,
+            heatmap,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> Texture {
+        let mut texture = Texture::new(4, 3, PixelFormat::RGBA8);
+        for (i, d) in texture.data.iter_mut().enumerate() {
+            *d = (i * 20) as u8;
+        }
+        texture
+    }
+
+    #[test]
+    fn identical_images_match() {
+        let report = gradient().diff(&gradient());
+        assert!(report.is_match());
+        assert_eq!(report.mismatched_pixels, 0);
+        assert_eq!(report.max_error, [0.0; 4]);
+        assert_eq!(report.psnr, f32::INFINITY);
+        assert_eq!((report.heatmap.width, report.heatmap.height), (4, 3));
+    }
+
+    #[test]
+    fn counts_differing_pixels() {
+        let mut other = gradient();
+        other.set_pixel(1, 2, &[255, 255, 255, 255]);
+        other.set_pixel(3, 0, &[0, 0, 0, 0]);
+        let report = gradient().diff(&other);
+        assert!(!report.is_match());
+        assert_eq!(report.mismatched_pixels, 2);
+        assert!(report.psnr.is_finite());
+        // Mismatches are drawn from red to yellow.
+        assert_eq!(report.heatmap.get_pixel(1, 2).unwrap()[0], 255);
+    }
+
+    #[test]
+    fn tolerance_accepts_small_errors() {
+        let mut other = gradient();
+        other.data[0] += 1;
+        assert!(!gradient().diff(&other).is_match());
+        let report = gradient().diff_with_tolerance(&other, [1.0 / 255.0 + 1e-6; 4]);
+        assert!(report.is_match());
+        assert_eq!(report.max_error[0], 1.0 / 255.0);
+        // Within tolerance but not identical shows as blue.
+        assert_eq!(report.heatmap.get_pixel(0, 0).unwrap(), [0, 0, 255]);
+    }
+
+    #[test]
+    fn compares_across_formats() {
+        let texture = gradient();
+        let float = texture.convert(PixelFormat::RGBA32F);
+        assert!(texture.diff(&float).is_match());
+    }
+
+    #[test]
+    fn size_mismatch_never_matches() {
+        let report = gradient().diff(&Texture::new(3, 3, PixelFormat::RGBA8));
+        assert!(!report.same_size);
+        assert!(!report.is_match());
+        // The column only `gradient` has.
+        assert!(report.mismatched_pixels >= 3);
+    }
+}
@@ -0,0 +1,385 @@
+//! CPU rasterization primitives. Every primitive clips to the texture bounds.
+
+use crate::Vec2;
+use crate::texture::Texture;
+
+// Outcodes for Cohen–Sutherland line clipping.
+const LEFT: u8 = 1;
+const RIGHT: u8 = 2;
+const TOP: u8 = 4;
+const BOTTOM: u8 = 8;
+
+fn outcode(p: Vec2, min: f32, max_x: f32, max_y: f32) -> u8 {
+    let mut code = 0;
+    if p.x < min {
+        code |= LEFT;
+    } else if p.x > max_x {
+        code |= RIGHT;
+    }
+    if p.y < min {
+        code |= TOP;
+    } else if p.y > max_y {
+        code |= BOTTOM;
+    }
+    code
+}
+
+/// Clip a segment to the box [min, max_x] × [min, max_y].
+/// Returns `None` if it is fully outside.
+fn clip_line(mut a: Vec2, mut b: Vec2, min: f32, max_x: f32, max_y: f32) -> Option<(Vec2, Vec2)> {
+    let mut code_a = outcode(a, min, max_x, max_y);
+    let mut code_b = outcode(b, min, max_x, max_y);
+    loop {
+        if code_a | code_b == 0 {
+            return Some((a, b));
+        }
+        if code_a & code_b != 0 {
+            return None;
+        }
+        let code = if code_a != 0 { code_a } else { code_b };
+        let p = if code & BOTTOM != 0 {
+            Vec2::new(a.x + (b.x - a.x) * (max_y - a.y) / (b.y - a.y), max_y)
+        } else if code & TOP != 0 {
+            Vec2::new(a.x + (b.x - a.x) * (min - a.y) / (b.y - a.y), min)
+        } else if code & RIGHT != 0 {
+            Vec2::new(max_x, a.y + (b.y - a.y) * (max_x - a.x) / (b.x - a.x))
+        } else {
+            Vec2::new(min, a.y + (b.y - a.y) * (min - a.x) / (b.x - a.x))
+        };
+        if code == code_a {
+            a = p;
+            code_a = outcode(a, min, max_x, max_y);
+        } else {
+            b = p;
+            code_b = outcode(b, min, max_x, max_y);
+        }
+    }
+}
+
+/// Fractional part that is always in [0, 1), also for negative values.
+fn fpart(v: f32) -> f32 {
+    v - v.floor()
+}
+
+impl Texture {
+    /// One pixel wide line using Bresenham's algorithm.
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: &[u8]) {
+        if self.width <= 0 || self.height <= 0 {
+            return;
+        }
+        let a = Vec2::new(x0 as f32, y0 as f32);
+        let b = Vec2::new(x1 as f32, y1 as f32);
+        let max_x = (self.width - 1) as f32;
+        let max_y = (self.height - 1) as f32;
+        let Some((a, b)) = clip_line(a, b, 0.0, max_x, max_y) else {
+            return;
+        };
+        let (mut x, mut y) = (a.x.round() as i32, a.y.round() as i32);
+        let (x1, y1) = (b.x.round() as i32, b.y.round() as i32);
+
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let sx = if x < x1 { 1 } else { -1 };
+        let sy = if y < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        loop {
+            self.set_pixel(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Antialiased one pixel wide line using Xiaolin Wu's algorithm.
+    /// Endpoints are pixel centers.
+    pub fn draw_line_aa(&mut self, a: Vec2, b: Vec2, color: &[u8]) {
+        if self.width <= 0 || self.height <= 0 {
+            return;
+        }
+        // Clip against a one pixel margin so edge coverage is preserved.
+        let max_x = self.width as f32;
+        let max_y = self.height as f32;
+        let Some((a, b)) = clip_line(a, b, -1.0, max_x, max_y) else {
+            return;
+        };
+        let steep = (b.y - a.y).abs() > (b.x - a.x).abs();
+        let (mut x0, mut y0, mut x1, mut y1) = if steep {
+            (a.y, a.x, b.y, b.x)
+        } else {
+            (a.x, a.y, b.x, b.y)
+        };
+        if x0 > x1 {
+            std::mem::swap(&mut x0, &mut x1);
+            std::mem::swap(&mut y0, &mut y1);
+        }
+        let dx = x1 - x0;
+        let gradient = if dx == 0.0 { 1.0 } else { (y1 - y0) / dx };
+
+        let plot = |tex: &mut Texture, x: i32, y: i32, c: f32| {
+            if steep {
+                tex.cover_pixel(y, x, color, c);
+            } else {
+                tex.cover_pixel(x, y, color, c);
+            }
+        };
+
+        // First endpoint.
+        let x_end = x0.round();
+        let y_end = y0 + gradient * (x_end - x0);
+        let x_gap = 1.0 - fpart(x0 + 0.5);
+        let px0 = x_end as i32;
+        plot(
+            self,
+            px0,
+            y_end.floor() as i32,
+            (1.0 - fpart(y_end)) * x_gap,
+        );
+        plot(self, px0, y_end.floor() as i32 + 1, fpart(y_end) * x_gap);
+        let mut inter_y = y_end + gradient;
+
+        // Second endpoint.
+        let x_end = x1.round();
+        let y_end = y1 + gradient * (x_end - x1);
+        let x_gap = fpart(x1 + 0.5);
+        let px1 = x_end as i32;
+        plot(
+            self,
+            px1,
+            y_end.floor() as i32,
+            (1.0 - fpart(y_end)) * x_gap,
+        );
+        plot(self, px1, y_end.floor() as i32 + 1, fpart(y_end) * x_gap);
+
+        for x in px0 + 1..px1 {
+            let y = inter_y.floor() as i32;
+            plot(self, x, y, 1.0 - fpart(inter_y));
+            plot(self, x, y + 1, fpart(inter_y));
+            inter_y += gradient;
+        }
+    }
+
+    /// Fill the axis-aligned rectangle covering pixels [x, x + w) × [y, y + h).
+    pub fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: &[u8]) {
+        let x0 = x.max(0);
+        let y0 = y.max(0);
+        let x1 = x.saturating_add(w).min(self.width);
+        let y1 = y.saturating_add(h).min(self.height);
+        for py in y0..y1 {
+            for px in x0..x1 {
+                self.set_pixel(px, py, color);
+            }
+        }
+    }
+
+    /// Outline of the rectangle [x, x + w) × [y, y + h), drawn inwards.
+    pub fn draw_rect(&mut self, x: i32, y: i32, w: i32, h: i32, thickness: i32, color: &[u8]) {
+        if w <= 0 || h <= 0 || thickness <= 0 {
+            return;
+        }
+        let t = thickness.min(w).min(h);
+        self.fill_rect(x, y, w, t, color);
+        self.fill_rect(x, y + h - t, w, t, color);
+        self.fill_rect(x, y + t, t, h - 2 * t, color);
+        self.fill_rect(x + w - t, y + t, t, h - 2 * t, color);
+    }
+
+    /// Fill a polygon with the even-odd rule, sampling at pixel centers.
+    /// Works for concave and self-intersecting outlines.
+    pub fn fill_polygon(&mut self, points: &[Vec2], color: &[u8]) {
+        if points.len() < 3 || self.width <= 0 || self.height <= 0 {
+            return;
+        }
+        let min_y = points.iter().map(|p| p.y).fold(f32::MAX, f32::min);
+        let max_y = points.iter().map(|p| p.y).fold(f32::MIN, f32::max);
+        let y_start = (min_y - 0.5).ceil().max(0.0) as i32;
+        let y_end = ((max_y - 0.5).floor() as i32).min(self.height - 1);
+
+        let mut crossings: Vec<f32> = Vec::new();
+        for y in y_start..=y_end {
+            let sy = y as f32 + 0.5;
+            crossings.clear();
+            for i in 0..points.len() {
+                let a = points[i];
+                let b = points[(i + 1) % points.len()];
+                // Half-open test so shared vertices are counted once.
+                if (a.y <= sy) != (b.y <= sy) {
+                    crossings.push(a.x + (sy - a.y) * (b.x - a.x) / (b.y - a.y));
+                }
+            }
+            crossings.sort_by(f32::total_cmp);
+            for pair in crossings.chunks_exact(2) {
+                let x0 = (pair[0] - 0.5).ceil().max(0.0) as i32;
+                let x1 = ((pair[1] - 0.5).floor() as i32).min(self.width - 1);
+                for x in x0..=x1 {
+                    self.set_pixel(x, y, color);
+                }
+            }
+        }
+    }
+
+    /// Outline of an axis-aligned ellipse using the midpoint algorithm.
+    pub fn draw_ellipse(&mut self, cx: i32, cy: i32, rx: i32, ry: i32, color: &[u8]) {
+        self.ellipse(cx, cy, rx, ry, color, false);
+    }
+
+    /// Filled axis-aligned ellipse.
+    pub fn fill_ellipse(&mut self, cx: i32, cy: i32, rx: i32, ry: i32, color: &[u8]) {
+        self.ellipse(cx, cy, rx, ry, color, true);
+    }
+
+    fn ellipse(&mut self, cx: i32, cy: i32, rx: i32, ry: i32, color: &[u8], fill: bool) {
+        if rx < 0 || ry < 0 {
+            return;
+        }
+        let plot4 = |tex: &mut Texture, x: i32, y: i32| {
+            if fill {
+                tex.fill_rect(cx - x, cy - y, 2 * x + 1, 1, color);
+                tex.fill_rect(cx - x, cy + y, 2 * x + 1, 1, color);
+            } else {
+                tex.set_pixel(cx + x, cy + y, color);
+                tex.set_pixel(cx - x, cy + y, color);
+                tex.set_pixel(cx + x, cy - y, color);
+                tex.set_pixel(cx - x, cy - y, color);
+            }
+        };
+
+        let rx2 = rx as i64 * rx as i64;
+        let ry2 = ry as i64 * ry as i64;
+        let (mut x, mut y) = (0i64, ry as i64);
+        let mut dx = 0i64;
+        let mut dy = 2 * rx2 * y;
+
+        // Region 1: slope magnitude below 1.
+        let mut p = ry2 - rx2 * ry as i64 + rx2 / 4;
+        while dx < dy {
+            plot4(self, x as i32, y as i32);
+            x += 1;
+            dx += 2 * ry2;
+            if p < 0 {
+                p += ry2 + dx;
+            } else {
+                y -= 1;
+                dy -= 2 * rx2;
+                p += ry2 + dx - dy;
+            }
+        }
+
+        // Region 2: slope magnitude above 1.
+        let mut p = (ry2 as f64 * (x as f64 + 0.5).powi(2) + rx2 as f64 * ((y - 1) as f64).powi(2)
+            - (rx2 * ry2) as f64) as i64;
+        while y >= 0 {
+            plot4(self, x as i32, y as i32);
+            y -= 1;
+            dy -= 2 * rx2;
+            if p > 0 {
+                p += rx2 - dy;
+            } else {
+                x += 1;
+                dx += 2 * ry2;
+                p += rx2 - dy + dx;
+            }
+        }
+    }
+
+    /// Filled disc with a real-valued center and radius.
+    fn fill_disc(&mut self, c: Vec2, radius: f32, color: &[u8]) {
+        let r2 = radius * radius;
+        let y0 = ((c.y - radius).floor() as i32).max(0);
+        let y1 = ((c.y + radius).ceil() as i32).min(self.height - 1);
+        let x0 = ((c.x - radius).floor() as i32).max(0);
+        let x1 = ((c.x + radius).ceil() as i32).min(self.width - 1);
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let dx = x as f32 + 0.5 - c.x;
+                let dy = y as f32 + 0.5 - c.y;
+                if dx * dx + dy * dy <= r2 {
+                    self.set_pixel(x, y, color);
+                }
+            }
+        }
+    }
+
+    /// Connected line segments of the given width with round joins and caps.
+    /// Coordinates are in pixel space where (0.5, 0.5) is the first pixel center.
+    pub fn draw_polyline(&mut self, points: &[Vec2], thickness: f32, color: &[u8]) {
+        let half = thickness * 0.5;
+        if points.is_empty() || half <= 0.0 {
+            return;
+        }
+        for pair in points.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let d = Vec2::new(b.x - a.x, b.y - a.y);
+            let len = (d.x * d.x + d.y * d.y).sqrt();
+            if len == 0.0 {
+                continue;
+            }
+            let n = Vec2::new(-d.y / len * half, d.x / len * half);
+            let quad = [
+                Vec2::new(a.x + n.x, a.y + n.y),
+                Vec2::new(b.x + n.x, b.y + n.y),
+                Vec2::new(b.x - n.x, b.y - n.y),
+                Vec2::new(a.x - n.x, a.y - n.y),
+            ];
+            self.fill_polygon(&quad, color);
+        }
+        for &p in points {
+            self.fill_disc(p, half, color);
+        }
+    }
+
+    /// Replace the 4-connected region of pixels matching the color at
+    /// (x, y) with `color`, using a scanline stack fill.
+    pub fn flood_fill(&mut self, x: i32, y: i32, color: &[u8]) {
+        let Some(target) = self.get_pixel(x, y).map(|p| p.to_vec()) else {
+            return;
+        };
+        if color.len() < target.len() || target[..] == color[..target.len()] {
+            return;
+        }
+
+        let matches = |tex: &Texture, x: i32, y: i32| tex.get_pixel(x, y) == Some(&target[..]);
+        let mut stack = vec![(x, y)];
+        while let Some((sx, sy)) = stack.pop() {
+            if !matches(self, sx, sy) {
+                continue;
+            }
+            let mut left = sx;
+            while left > 0 && matches(self, left - 1, sy) {
+                left -= 1;
+            }
+            let mut right = sx;
+            while right + 1 < self.width && matches(self, right + 1, sy) {
+                right += 1;
+            }
+            for px in left..=right {
+                self.set_pixel(px, sy, color);
+            }
+            for ny in [sy - 1, sy + 1] {
+                if ny < 0 || ny >= self.height {
+                    continue;
+                }
+                // Push one seed per run of matching pixels in the neighbour row.
+                let mut in_run = false;
+                for px in left..=right {
+                    if matches(self, px, ny) {
+                        if !in_run {
+                            stack.push((px, ny));
+                            in_run = true;
+                        }
+                    } else {
+                        in_run = false;
+                    }
+                }
+            }
+        }
+    }
+}
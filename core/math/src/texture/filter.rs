@@ -0,0 +1,239 @@
+//! Convolution and morphology filters.
+//!
+//! Filters return a new texture and sample outside the image by clamping
+//! to the nearest edge pixel. Textures with alpha are convolved with
+//! premultiplied alpha so transparent pixels do not bleed their color. Work
+//! is split across threads by rows.
+
+use crate::texture::float_image::FloatImage;
+use crate::texture::{PixelFormat, Texture};
+use std::thread;
+
+/// A 2D convolution kernel with its anchor at the center.
+#[derive(Clone, Debug)]
+pub struct Kernel {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major weights, `width * height` long.
+    pub weights: Vec<f32>,
+}
+
+impl Kernel {
+    /// Width and height should be odd so the kernel has a center pixel.
+    /// `Texture::convolve` leaves the texture unchanged if `weights` is not
+    /// `width * height` long.
+    pub fn new(width: usize, height: usize, weights: Vec<f32>) -> Self {
+        Kernel {
+            width,
+            height,
+            weights,
+        }
+    }
+
+    pub fn sharpen(amount: f32) -> Self {
+        let a = amount;
+        Kernel::new(
+            3,
+            3,
+            vec![0.0, -a, 0.0, -a, 1.0 + 4.0 * a, -a, 0.0, -a, 0.0],
+        )
+    }
+}
+
+/// Normalized 1D gaussian with a radius of three standard deviations.
+fn gaussian_weights(sigma: f32) -> Vec<f32> {
+    let radius = (sigma * 3.0).ceil() as i32;
+    let mut w: Vec<f32> = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = w.iter().sum();
+    w.iter_mut().for_each(|v| *v /= sum);
+    w
+}
+
+/// Run `f(y, row)` for every row of `out`, spreading rows across threads.
+pub(crate) fn par_rows<T: Send>(out: &mut [T], row_len: usize, f: impl Fn(usize, &mut [T]) + Sync) {
+    if row_len == 0 {
+        return;
+    }
+    let rows = out.len() / row_len;
+    let threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(rows.max(1));
+    let rows_per_thread = rows.div_ceil(threads);
+    if threads <= 1 {
+        for (y, row) in out.chunks_mut(row_len).enumerate() {
+            f(y, row);
+        }
+        return;
+    }
+    let f = &f;
+    thread::scope(|s| {
+        for (i, block) in out.chunks_mut(rows_per_thread * row_len).enumerate() {
+            s.spawn(move || {
+                for (j, row) in block.chunks_mut(row_len).enumerate() {
+                    f(i * rows_per_thread + j, row);
+                }
+            });
+        }
+    });
+}
+
+impl FloatImage {
+    fn convolve(&self, kernel: &Kernel) -> FloatImage {
+        let (w, ch) = (self.width, self.channels);
+        let (kw, kh) = (kernel.width as isize, kernel.height as isize);
+        let mut data = vec![0f32; self.data.len()];
+        par_rows(&mut data, w * ch, |y, row| {
+            for x in 0..w {
+                for c in 0..ch {
+                    let mut sum = 0.0;
+                    for ky in 0..kh {
+                        for kx in 0..kw {
+                            let weight = kernel.weights[(ky * kw + kx) as usize];
+                            let sx = x as isize + kx - kw / 2;
+                            let sy = y as isize + ky - kh / 2;
+                            sum += weight * self.get(sx, sy, c);
+                        }
+                    }
+                    row[x * ch + c] = sum;
+                }
+            }
+        });
+        FloatImage { data, ..*self }
+    }
+
+    fn convolve_separable(&self, weights: &[f32]) -> FloatImage {
+        let horizontal = Kernel::new(weights.len(), 1, weights.to_vec());
+        let vertical = Kernel::new(1, weights.len(), weights.to_vec());
+        self.convolve(&horizontal).convolve(&vertical)
+    }
+}
+
+impl Texture {
+    fn is_filterable(&self) -> bool {
+        self.width > 0
+            && self.height > 0
+            && self.data.len()
+                >= self.width as usize * self.height as usize * self.format.bytes_per_pixel()
+    }
+
+    /// Convolve with an arbitrary kernel.
+    pub fn convolve(&self, kernel: &Kernel) -> Texture {
+        if !self.is_filterable()
+            || kernel.weights.is_empty()
+            || kernel.width.checked_mul(kernel.height) != Some(kernel.weights.len())
+        {
+            return self.clone();
+        }
+        FloatImage::from_texture(self, true)
+            .convolve(kernel)
+            .into_texture(self.format, true)
+    }
+
+    /// Separable gaussian blur with standard deviation `sigma` in pixels.
+    pub fn gaussian_blur(&self, sigma: f32) -> Texture {
+        if !self.is_filterable() || sigma <= 0.0 {
+            return self.clone();
+        }
+        FloatImage::from_texture(self, true)
+            .convolve_separable(&gaussian_weights(sigma))
+            .into_texture(self.format, true)
+    }
+
+    /// Separable box blur over a (2 * radius + 1) square window.
+    pub fn box_blur(&self, radius: i32) -> Texture {
+        if !self.is_filterable() || radius <= 0 {
+            return self.clone();
+        }
+        let n = (2 * radius + 1) as usize;
+        FloatImage::from_texture(self, true)
+            .convolve_separable(&vec![1.0 / n as f32; n])
+            .into_texture(self.format, true)
+    }
+
+    /// Sharpen with a 3×3 Laplacian kernel; `amount` of 1.0 is a typical strength.
+    pub fn sharpen(&self, amount: f32) -> Texture {
+        self.convolve(&Kernel::sharpen(amount))
+    }
+
+    /// Sobel edge magnitude of the luminance, as an `R8` texture.
+    pub fn sobel(&self) -> Texture {
+        if !self.is_filterable() {
+            return Texture::new(self.width.max(0), self.height.max(0), PixelFormat::R8);
+        }
+        let gray = self.to_luminance();
+        let gx = Kernel::new(3, 3, vec![-1.0, 0.0, 1.0, -2.0, 0.0, 2.0, -1.0, 0.0, 1.0]);
+        let gy = Kernel::new(3, 3, vec![-1.0, -2.0, -1.0, 0.0, 0.0, 0.0, 1.0, 2.0, 1.0]);
+        let ex = gray.convolve(&gx);
+        let ey = gray.convolve(&gy);
+        let mut out = Texture::new(self.width, self.height, PixelFormat::R8);
+        for (d, (x, y)) in out.data.iter_mut().zip(ex.data.iter().zip(&ey.data)) {
+            // The largest possible response of a [0, 1] image is 4 per axis.
+            *d = ((x * x + y * y).sqrt() / 4.0 * 255.0)
+                .round()
+                .clamp(0.0, 255.0) as u8;
+        }
+        out
+    }
+
+    fn to_luminance(&self) -> FloatImage {
+        let format = self.format;
+        let data = self
+            .data
+            .chunks_exact(format.bytes_per_pixel())
+            .take(self.width.max(0) as usize * self.height.max(0) as usize)
+            .map(|px| {
+                let [r, g, b, _] = format.read_rgba(px);
+                0.2126 * r + 0.7152 * g + 0.0722 * b
+            })
+            .collect();
+        FloatImage {
+            width: self.width as usize,
+            height: self.height as usize,
+            channels: 1,
+            data,
+        }
+    }
+
+    /// Grow bright regions: per-channel maximum over a (2 * radius + 1) square.
+    pub fn dilate(&self, radius: i32) -> Texture {
+        self.morphology(radius, f32::max)
+    }
+
+    /// Shrink bright regions: per-channel minimum over a (2 * radius + 1) square.
+    pub fn erode(&self, radius: i32) -> Texture {
+        self.morphology(radius, f32::min)
+    }
+
+    fn morphology(&self, radius: i32, pick: fn(f32, f32) -> f32) -> Texture {
+        if !self.is_filterable() || radius <= 0 {
+            return self.clone();
+        }
+        let src = FloatImage::from_texture(self, false);
+        let (w, ch) = (src.width, src.channels);
+        let pass = |img: &FloatImage, horizontal: bool| -> FloatImage {
+            let mut data = vec![0f32; img.data.len()];
+            par_rows(&mut data, w * ch, |y, row| {
+                for x in 0..w {
+                    for c in 0..ch {
+                        let mut v = img.get(x as isize, y as isize, c);
+                        for k in -radius as isize..=radius as isize {
+                            let (sx, sy) = if horizontal {
+                                (x as isize + k, y as isize)
+                            } else {
+                                (x as isize, y as isize + k)
+                            };
+                            v = pick(v, img.get(sx, sy, c));
+                        }
+                        row[x * ch + c] = v;
+                    }
+                }
+            });
+            FloatImage { data, ..*img }
+        };
+        // A square window is separable for min and max.
+        pass(&pass(&src, true), false).into_texture(self.format, false)
+    }
+}
@@ -0,0 +1,126 @@
+use crate::texture::Texture;
+use crate::texture::pixel_format::PixelFormat;
+
+/// Floating point working copy of a texture, one `f32` per channel.
+/// Values are normalized (8-bit channels map to [0, 1]) and color is
+/// optionally premultiplied by alpha for formats that have it.
+pub(crate) struct FloatImage {
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub data: Vec<f32>,
+}
+
+pub(crate) fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub(crate) fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+impl FloatImage {
+    pub fn from_texture(tex: &Texture, premultiply: bool) -> Self {
+        let format = tex.format;
+        let (w, h, ch) = (
+            tex.width.max(0) as usize,
+            tex.height.max(0) as usize,
+            format.channels(),
+        );
+        let mut data = vec![0f32; w * h * ch];
+        for (dst, px) in data
+            .chunks_exact_mut(ch)
+            .zip(tex.data.chunks_exact(format.bytes_per_pixel()))
+        {
+            for (c, d) in dst.iter_mut().enumerate() {
+                *d = format.read_channel(px, c);
+            }
+        }
+        let mut image = FloatImage {
+            width: w,
+            height: h,
+            channels: ch,
+            data,
+        };
+        if premultiply {
+            image.premultiply();
+        }
+        image
+    }
+
+    /// Store back into a texture of `format`, undoing premultiplication.
+    pub fn into_texture(mut self, format: PixelFormat, premultiplied: bool) -> Texture {
+        if premultiplied {
+            self.unpremultiply();
+        }
+        let ch = self.channels;
+        let mut tex = Texture::new(self.width as i32, self.height as i32, format);
+        for (dst, px) in tex
+            .data
+            .chunks_exact_mut(format.bytes_per_pixel())
+            .zip(self.data.chunks_exact(ch))
+        {
+            for (c, &v) in px.iter().enumerate() {
+                format.write_channel(dst, c, v);
+            }
+        }
+        tex
+    }
+
+    /// Number of leading channels holding color rather than alpha.
+    fn color_channels(&self) -> usize {
+        match self.channels {
+            2 => 1,
+            4 => 3,
+            n => n,
+        }
+    }
+
+    /// Multiply color by alpha. Images without alpha are unaffected.
+    pub fn premultiply(&mut self) {
+        let (ch, color) = (self.channels, self.color_channels());
+        if color == ch {
+            return;
+        }
+        for px in self.data.chunks_exact_mut(ch) {
+            let a = px[color];
+            px[..color].iter_mut().for_each(|v| *v *= a);
+        }
+    }
+
+    pub fn unpremultiply(&mut self) {
+        let (ch, color) = (self.channels, self.color_channels());
+        if color == ch {
+            return;
+        }
+        for px in self.data.chunks_exact_mut(ch) {
+            let a = px[color];
+            px[..color]
+                .iter_mut()
+                .for_each(|v| *v = if a > 0.0 { *v / a } else { 0.0 });
+        }
+    }
+
+    /// Apply `f` to every color channel, leaving alpha untouched.
+    pub fn map_color(&mut self, f: fn(f32) -> f32) {
+        let (ch, color) = (self.channels, self.color_channels());
+        for px in self.data.chunks_exact_mut(ch) {
+            px[..color].iter_mut().for_each(|v| *v = f(*v));
+        }
+    }
+
+    /// Channel `c` at (x, y) with coordinates clamped to the edge.
+    pub fn get(&self, x: isize, y: isize, c: usize) -> f32 {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.data[(y * self.width + x) * self.channels + c]
+    }
+}
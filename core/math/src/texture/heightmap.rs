@@ -0,0 +1,107 @@
+//! Normal and ambient occlusion maps baked from heightmaps.
+//!
+//! Heights are the luminance of each pixel, normalized to [0, 1] for 8-bit
+//! formats and used as stored for float formats.
+
+use crate::texture::filter::par_rows;
+use crate::texture::mip::Wrap;
+use crate::texture::{PixelFormat, Texture};
+use crate::vec3::Vec3;
+
+impl Texture {
+    fn heights(&self) -> Vec<f32> {
+        let bpp = self.format.bytes_per_pixel();
+        self.data
+            .chunks_exact(bpp)
+            .take(self.width.max(0) as usize * self.height.max(0) as usize)
+            .map(|px| {
+                let [r, g, b, _] = self.format.read_rgba(px);
+                0.2126 * r + 0.7152 * g + 0.0722 * b
+            })
+            .collect()
+    }
+
+    /// Tangent-space normal map as `RGB8`, encoded as `n * 0.5 + 0.5`.
+    /// `strength` is the height difference, in pixels, of a full [0, 1]
+    /// height step. Normals follow the OpenGL convention: green points up
+    /// the image (towards row 0).
+    pub fn normal_map(&self, strength: f32, wrap: Wrap) -> Texture {
+        let (w, h) = (self.width.max(0), self.height.max(0));
+        let mut out = Texture::new(w, h, PixelFormat::RGB8);
+        let heights = self.heights();
+        if heights.len() < (w * h) as usize {
+            return out;
+        }
+        let at = |x: i32, y: i32| heights[(wrap.apply(y, h) * w + wrap.apply(x, w)) as usize];
+        par_rows(&mut out.data, w as usize * 3, |y, row| {
+            let y = y as i32;
+            for x in 0..w {
+                // Sobel gradient, which is less noisy than central differences.
+                let dx = (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1))
+                    - (at(x - 1, y - 1) + 2.0 * at(x - 1, y) + at(x - 1, y + 1));
+                let dy = (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1))
+                    - (at(x - 1, y - 1) + 2.0 * at(x, y - 1) + at(x + 1, y - 1));
+                let (dx, dy) = (dx / 8.0 * strength, dy / 8.0 * strength);
+                let n = Vec3::new(-dx, dy, 1.0).normalized();
+                let q = |v: f32| ((v * 0.5 + 0.5) * 255.0).round() as u8;
+                let i = x as usize * 3;
+                row[i..i + 3].copy_from_slice(&[q(n.x), q(n.y), q(n.z)]);
+            }
+        });
+        out
+    }
+
+    /// Horizon-based ambient occlusion as `R8`, 255 meaning unoccluded.
+    /// Each pixel marches `directions` rays out to `radius` pixels and
+    /// records the highest horizon; `height_scale` is the height in pixels
+    /// of a full [0, 1] height step.
+    pub fn horizon_ao(
+        &self,
+        height_scale: f32,
+        radius: i32,
+        directions: usize,
+        wrap: Wrap,
+    ) -> Texture {
+        let (w, h) = (self.width.max(0), self.height.max(0));
+        let mut out = Texture::new(w, h, PixelFormat::R8);
+        let heights = self.heights();
+        if heights.len() < (w * h) as usize || radius <= 0 || directions == 0 {
+            out.data.fill(255);
+            return out;
+        }
+        let at = |x: i32, y: i32| heights[(wrap.apply(y, h) * w + wrap.apply(x, w)) as usize];
+        let dirs: Vec<(f32, f32)> = (0..directions)
+            .map(|i| {
+                let a = i as f32 / directions as f32 * std::f32::consts::TAU;
+                (a.cos(), a.sin())
+            })
+            .collect();
+        par_rows(&mut out.data, w as usize, |y, row| {
+            let y = y as i32;
+            for x in 0..w {
+                let h0 = at(x, y) * height_scale;
+                let mut occlusion = 0.0;
+                for &(cx, cy) in &dirs {
+                    // Sine of the highest elevation angle seen along the ray.
+                    let mut horizon = 0f32;
+                    for step in 1..=radius {
+                        let d = step as f32;
+                        let sx = x + (cx * d).round() as i32;
+                        let sy = y + (cy * d).round() as i32;
+                        if wrap == Wrap::Clamp && (sx < 0 || sy < 0 || sx >= w || sy >= h) {
+                            break;
+                        }
+                        let rise = at(sx, sy) * height_scale - h0;
+                        if rise > 0.0 {
+                            horizon = horizon.max(rise / (rise * rise + d * d).sqrt());
+                        }
+                    }
+                    occlusion += horizon;
+                }
+                let ao = 1.0 - occlusion / dirs.len() as f32;
+                row[x as usize] = (ao.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        });
+        out
+    }
+}
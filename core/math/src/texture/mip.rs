@@ -0,0 +1,242 @@
+//! Mip chain generation and CPU texture sampling.
+//!
+//! Texture coordinates follow the GPU convention for uploaded textures:
+//! (0, 0) is the top-left corner of row 0 and (1, 1) the bottom-right corner,
+//! with texel centers at `(i + 0.5) / size`.
+
+use crate::texture::Texture;
+use crate::texture::float_image::{FloatImage, linear_to_srgb, srgb_to_linear};
+use crate::texture::resample::{ResizeFilter, compute_taps_with};
+use crate::{Color, Vec2};
+use std::f32::consts::PI;
+
+/// How coordinates outside [0, 1] are mapped back into the texture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Wrap {
+    #[default]
+    Repeat,
+    Clamp,
+    /// Repeat, flipping every other tile.
+    Mirror,
+}
+
+impl Wrap {
+    /// Map texel index `i` into [0, n).
+    pub fn apply(self, i: i32, n: i32) -> i32 {
+        match self {
+            Wrap::Repeat => i.rem_euclid(n),
+            Wrap::Clamp => i.clamp(0, n - 1),
+            Wrap::Mirror => {
+                let m = i.rem_euclid(2 * n);
+                if m >= n { 2 * n - 1 - m } else { m }
+            }
+        }
+    }
+}
+
+/// Downsampling filter used between mip levels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MipFilter {
+    /// Average of the 2×2 block, the classic GPU mipmap.
+    #[default]
+    Box,
+    /// Kaiser-windowed sinc; sharper than box with little ringing.
+    Kaiser,
+}
+
+/// Modified Bessel function of the first kind, order zero.
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let q = x * x / 4.0;
+    for k in 1..32 {
+        term *= q / (k * k) as f32;
+        sum += term;
+        if term < sum * 1e-7 {
+            break;
+        }
+    }
+    sum
+}
+
+impl MipFilter {
+    fn radius(self) -> f32 {
+        match self {
+            MipFilter::Box => 0.5,
+            MipFilter::Kaiser => 3.0,
+        }
+    }
+
+    fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            MipFilter::Box => {
+                if x <= 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            MipFilter::Kaiser => {
+                const ALPHA: f32 = 4.0;
+                let width = self.radius();
+                if x >= width {
+                    return 0.0;
+                }
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    (PI * x).sin() / (PI * x)
+                };
+                let t = x / width;
+                sinc * bessel_i0(ALPHA * (1.0 - t * t).sqrt()) / bessel_i0(ALPHA)
+            }
+        }
+    }
+}
+
+/// A texture and its successively halved levels, down to 1×1.
+#[derive(Clone, Debug)]
+pub struct MipChain {
+    /// Level 0 is the full resolution texture.
+    pub levels: Vec<Texture>,
+}
+
+impl MipChain {
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// Level of detail for a pixel whose texture coordinates change by
+    /// `duv_dx` and `duv_dy` per screen pixel.
+    pub fn lod(&self, duv_dx: Vec2, duv_dy: Vec2) -> f32 {
+        let Some(base) = self.levels.first() else {
+            return 0.0;
+        };
+        let (w, h) = (base.width as f32, base.height as f32);
+        let len = |d: Vec2| ((d.x * w).powi(2) + (d.y * h).powi(2)).sqrt();
+        len(duv_dx).max(len(duv_dy)).max(1e-8).log2().max(0.0)
+    }
+
+    /// Sample with linear interpolation between the two nearest levels.
+    pub fn sample(&self, uv: Vec2, lod: f32, filter: ResizeFilter, wrap: Wrap) -> Color {
+        if self.levels.is_empty() {
+            return Color::new(0.0, 0.0, 0.0, 0.0);
+        }
+        let lod = lod.clamp(0.0, (self.levels.len() - 1) as f32);
+        let lo = lod.floor() as usize;
+        let t = lod - lo as f32;
+        let a = self.levels[lo].sample(uv, filter, wrap);
+        if t == 0.0 || lo + 1 >= self.levels.len() {
+            return a;
+        }
+        let b = self.levels[lo + 1].sample(uv, filter, wrap);
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Color::new(mix(a.r, b.r), mix(a.g, b.g), mix(a.b, b.b), mix(a.a, b.a))
+    }
+}
+
+impl Texture {
+    /// Build the full mip chain, each level half the size of the previous one.
+    /// With `srgb`, the color channels of 8-bit formats are treated as sRGB
+    /// encoded and averaged in linear light; float formats are always linear.
+    /// Levels are filtered from a floating point copy, so rounding does not
+    /// accumulate down the chain.
+    pub fn mip_chain(&self, filter: MipFilter, srgb: bool) -> MipChain {
+        let mut levels = vec![self.clone()];
+        if self.width <= 0 || self.height <= 0 {
+            return MipChain { levels };
+        }
+        let srgb = srgb && self.format.is_8bit();
+        let mut image = FloatImage::from_texture(self, false);
+        if srgb {
+            image.map_color(srgb_to_linear);
+        }
+        image.premultiply();
+
+        while image.width > 1 || image.height > 1 {
+            let (w, h) = ((image.width / 2).max(1), (image.height / 2).max(1));
+            let x_taps = compute_taps_with(image.width, w, filter.radius(), |x| filter.weight(x));
+            let y_taps = compute_taps_with(image.height, h, filter.radius(), |x| filter.weight(x));
+            image = image.resample(&x_taps, &y_taps);
+
+            let mut level = FloatImage {
+                data: image.data.clone(),
+                ..image
+            };
+            level.unpremultiply();
+            if srgb {
+                level.map_color(linear_to_srgb);
+            }
+            levels.push(level.into_texture(self.format, false));
+        }
+        MipChain { levels }
+    }
+
+    /// Sample at `uv` with `filter` as the reconstruction kernel.
+    /// Returns normalized RGBA; textures with alpha are filtered premultiplied.
+    pub fn sample(&self, uv: Vec2, filter: ResizeFilter, wrap: Wrap) -> Color {
+        let (w, h) = (self.width, self.height);
+        if w <= 0 || h <= 0 {
+            return Color::new(0.0, 0.0, 0.0, 0.0);
+        }
+        let texel = |x: i32, y: i32| -> [f32; 4] {
+            let mut px = self
+                .get_pixel_f32(wrap.apply(x, w), wrap.apply(y, h))
+                .unwrap_or([0.0; 4]);
+            let a = px[3];
+            px[..3].iter_mut().for_each(|v| *v *= a);
+            px
+        };
+
+        // Position in texel space, with texel centers on integers.
+        let x = uv.x * w as f32 - 0.5;
+        let y = uv.y * h as f32 - 0.5;
+        let mut acc = [0f32; 4];
+        if filter == ResizeFilter::Nearest {
+            acc = texel((x + 0.5).floor() as i32, (y + 0.5).floor() as i32);
+        } else {
+            let r = filter.radius();
+            let (x0, x1) = ((x - r).ceil() as i32, (x + r).floor() as i32);
+            let (y0, y1) = ((y - r).ceil() as i32, (y + r).floor() as i32);
+            let mut total = 0.0;
+            for ty in y0..=y1 {
+                let wy = filter.weight(ty as f32 - y);
+                if wy == 0.0 {
+                    continue;
+                }
+                for tx in x0..=x1 {
+                    let weight = wy * filter.weight(tx as f32 - x);
+                    if weight == 0.0 {
+                        continue;
+                    }
+                    let px = texel(tx, ty);
+                    for (a, v) in acc.iter_mut().zip(px) {
+                        *a += v * weight;
+                    }
+                    total += weight;
+                }
+            }
+            if total != 0.0 {
+                acc.iter_mut().for_each(|v| *v /= total);
+            }
+        }
+
+        let a = acc[3];
+        let mut rgba = [
+            if a > 0.0 { acc[0] / a } else { 0.0 },
+            if a > 0.0 { acc[1] / a } else { 0.0 },
+            if a > 0.0 { acc[2] / a } else { 0.0 },
+            a,
+        ];
+        if self.format.is_8bit() {
+            // Cubic and Lanczos kernels can overshoot the stored range.
+            rgba.iter_mut().for_each(|v| *v = v.clamp(0.0, 1.0));
+        }
+        Color::new(rgba[0], rgba[1], rgba[2], rgba[3])
+    }
+}
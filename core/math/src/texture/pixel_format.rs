@@ -0,0 +1,273 @@
+//! Pixel storage formats and conversion between them.
+//!
+//! Single channel formats hold luminance and `RG8` holds luminance + alpha,
+//! matching how `[u8]` colors are interpreted by the blend functions. The
+//! renderers swizzle GL textures of these formats to sample the same way.
+
+use crate::texture::Texture;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    R8,
+    RG8,
+    RGB8,
+    RGBA8,
+    /// Half precision float, one channel.
+    R16F,
+    /// Single precision float, four channels.
+    RGBA32F,
+    /// Single precision float depth.
+    Depth,
+}
+
+impl PixelFormat {
+    pub fn channels(self) -> usize {
+        match self {
+            PixelFormat::R8 | PixelFormat::R16F | PixelFormat::Depth => 1,
+            PixelFormat::RG8 => 2,
+            PixelFormat::RGB8 => 3,
+            PixelFormat::RGBA8 | PixelFormat::RGBA32F => 4,
+        }
+    }
+
+    pub fn bytes_per_channel(self) -> usize {
+        match self {
+            PixelFormat::R8 | PixelFormat::RG8 | PixelFormat::RGB8 | PixelFormat::RGBA8 => 1,
+            PixelFormat::R16F => 2,
+            PixelFormat::RGBA32F | PixelFormat::Depth => 4,
+        }
+    }
+
+    pub fn bytes_per_pixel(self) -> usize {
+        self.channels() * self.bytes_per_channel()
+    }
+
+    /// True for the formats with one unsigned byte per channel.
+    pub fn is_8bit(self) -> bool {
+        self.bytes_per_channel() == 1
+    }
+
+    pub fn has_alpha(self) -> bool {
+        matches!(
+            self,
+            PixelFormat::RG8 | PixelFormat::RGBA8 | PixelFormat::RGBA32F
+        )
+    }
+
+    /// The 8-bit format with `channels` channels.
+    pub fn from_channels(channels: usize) -> Option<PixelFormat> {
+        match channels {
+            1 => Some(PixelFormat::R8),
+            2 => Some(PixelFormat::RG8),
+            3 => Some(PixelFormat::RGB8),
+            4 => Some(PixelFormat::RGBA8),
+            _ => None,
+        }
+    }
+
+    /// Read channel `c` of one stored pixel. 8-bit values are normalized to [0, 1].
+    pub(crate) fn read_channel(self, pixel: &[u8], c: usize) -> f32 {
+        match self.bytes_per_channel() {
+            1 => pixel[c] as f32 / 255.0,
+            2 => f16_to_f32(u16::from_le_bytes([pixel[c * 2], pixel[c * 2 + 1]])),
+            _ => f32::from_le_bytes(pixel[c * 4..c * 4 + 4].try_into().unwrap()),
+        }
+    }
+
+    /// Write channel `c` of one stored pixel. 8-bit values are clamped to [0, 1].
+    pub(crate) fn write_channel(self, pixel: &mut [u8], c: usize, v: f32) {
+        match self.bytes_per_channel() {
+            1 => pixel[c] = (v.clamp(0.0, 1.0) * 255.0).round() as u8,
+            2 => pixel[c * 2..c * 2 + 2].copy_from_slice(&f32_to_f16(v).to_le_bytes()),
+            _ => pixel[c * 4..c * 4 + 4].copy_from_slice(&v.to_le_bytes()),
+        }
+    }
+
+    /// Expand one stored pixel to RGBA.
+    pub(crate) fn read_rgba(self, pixel: &[u8]) -> [f32; 4] {
+        let r = |c| self.read_channel(pixel, c);
+        match self.channels() {
+            1 => [r(0), r(0), r(0), 1.0],
+            2 => [r(0), r(0), r(0), r(1)],
+            3 => [r(0), r(1), r(2), 1.0],
+            _ => [r(0), r(1), r(2), r(3)],
+        }
+    }
+
+    /// Store RGBA into one pixel, reducing to luminance for 1-2 channel formats.
+    pub(crate) fn write_rgba(self, pixel: &mut [u8], rgba: [f32; 4]) {
+        let lum = 0.2126 * rgba[0] + 0.7152 * rgba[1] + 0.0722 * rgba[2];
+        match self.channels() {
+            1 => self.write_channel(pixel, 0, lum),
+            2 => {
+                self.write_channel(pixel, 0, lum);
+                self.write_channel(pixel, 1, rgba[3]);
+            }
+            n => {
+                for (c, &v) in rgba.iter().enumerate().take(n) {
+                    self.write_channel(pixel, c, v);
+                }
+            }
+        }
+    }
+}
+
+/// Convert IEEE 754 half precision bits to `f32`.
+pub fn f16_to_f32(h: u16) -> f32 {
+    let sign = ((h >> 15) as u32) << 31;
+    let exp = ((h >> 10) & 0x1f) as u32;
+    let mant = (h & 0x3ff) as u32;
+    let bits = match exp {
+        0 if mant == 0 => sign,
+        0 => {
+            // Subnormal: renormalize the mantissa.
+            let shift = mant.leading_zeros() - 21;
+            let mant = (mant << shift) & 0x3ff;
+            sign | ((113 - shift) << 23) | (mant << 13)
+        }
+        0x1f => sign | 0x7f80_0000 | (mant << 13),
+        _ => sign | ((exp + 112) << 23) | (mant << 13),
+    };
+    f32::from_bits(bits)
+}
+
+/// Convert `f32` to IEEE 754 half precision bits, rounding to nearest even.
+pub fn f32_to_f16(v: f32) -> u16 {
+    let bits = v.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mant = bits & 0x7f_ffff;
+
+    if exp == 0xff {
+        let nan = if mant != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+    let e = exp - 127 + 15;
+    if e >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if e <= 0 {
+        if e < -10 {
+            return sign;
+        }
+        // Subnormal half: shift in the implicit bit and round.
+        let m = mant | 0x80_0000;
+        let shift = (14 - e) as u32;
+        let half = 1 << (shift - 1);
+        let rounded = m + half - 1 + ((m >> shift) & 1);
+        return sign | (rounded >> shift) as u16;
+    }
+    let rounded = mant + 0xfff + ((mant >> 13) & 1);
+    if rounded & 0x80_0000 != 0 {
+        // Mantissa overflowed into the exponent.
+        let e = e + 1;
+        if e >= 0x1f {
+            return sign | 0x7c00;
+        }
+        return sign | ((e as u16) << 10);
+    }
+    sign | ((e as u16) << 10) | (rounded >> 13) as u16
+}
+
+impl Texture {
+    /// Convert every pixel to `format`. 8-bit targets clamp to [0, 1];
+    /// single channel targets store luminance.
+    pub fn convert(&self, format: PixelFormat) -> Texture {
+        if format == self.format {
+            return self.clone();
+        }
+        let mut out = Texture::new(self.width, self.height, format);
+        let src_bpp = self.format.bytes_per_pixel();
+        let dst_bpp = format.bytes_per_pixel();
+        for (src, dst) in self
+            .data
+            .chunks_exact(src_bpp)
+            .zip(out.data.chunks_exact_mut(dst_bpp))
+        {
+            format.write_rgba(dst, self.format.read_rgba(src));
+        }
+        out
+    }
+
+    /// Read a pixel of any format as RGBA; 8-bit channels are normalized to [0, 1].
+    pub fn get_pixel_f32(&self, x: i32, y: i32) -> Option<[f32; 4]> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return None;
+        }
+        let start = self.index(x, y);
+        let pixel = self
+            .data
+            .get(start..start + self.format.bytes_per_pixel())?;
+        Some(self.format.read_rgba(pixel))
+    }
+
+    /// Write a pixel of any format from RGBA. Out of bounds writes are ignored.
+    pub fn set_pixel_f32(&mut self, x: i32, y: i32, rgba: [f32; 4]) {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return;
+        }
+        let start = self.index(x, y);
+        let bpp = self.format.bytes_per_pixel();
+        if let Some(pixel) = self.data.get_mut(start..start + bpp) {
+            self.format.write_rgba(pixel, rgba);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_known_f16_values() {
+        for (h, v) in [
+            (0x0000, 0.0),
+            (0x8000, -0.0),
+            (0x3c00, 1.0),
+            (0xc000, -2.0),
+            (0x3555, 0.333_251_95),
+            (0x7bff, 65504.0),
+            (0x0400, 6.103_515_6e-5),
+            (0x0001, 5.960_464_5e-8),
+            (0x7c00, f32::INFINITY),
+            (0xfc00, f32::NEG_INFINITY),
+        ] {
+            assert_eq!(f16_to_f32(h).to_bits(), v.to_bits(), "{:#06x}", h);
+            assert_eq!(f32_to_f16(v), h, "{}", v);
+        }
+        assert!(f16_to_f32(0x7e00).is_nan());
+        assert_eq!(f32_to_f16(f32::NAN) & 0x7c00, 0x7c00);
+        assert_ne!(f32_to_f16(f32::NAN) & 0x3ff, 0);
+    }
+
+    #[test]
+    fn round_trips_every_f16() {
+        for h in 0..=u16::MAX {
+            let v = f16_to_f32(h);
+            if !v.is_nan() {
+                assert_eq!(f32_to_f16(v), h, "{:#06x}", h);
+            }
+        }
+    }
+
+    #[test]
+    fn rounds_f32_to_nearest_even() {
+        let ulp = 2f32.powi(-10);
+        // Halfway cases go to the even mantissa.
+        assert_eq!(f32_to_f16(1.0 + ulp / 2.0), 0x3c00);
+        assert_eq!(f32_to_f16(1.0 + ulp * 1.5), 0x3c02);
+        assert_eq!(f32_to_f16(1.0 + ulp * 0.75), 0x3c01);
+        // Past the largest half rounds to infinity, tiny values to zero.
+        assert_eq!(f32_to_f16(65520.0), 0x7c00);
+        assert_eq!(f32_to_f16(1e-10), 0x0000);
+        assert_eq!(f32_to_f16(-1e-10), 0x8000);
+    }
+
+    #[test]
+    fn reads_and_writes_half_float_channels() {
+        let mut pixel = [0u8; 2];
+        PixelFormat::R16F.write_channel(&mut pixel, 0, 0.5);
+        assert_eq!(u16::from_le_bytes(pixel), 0x3800);
+        assert_eq!(PixelFormat::R16F.read_channel(&pixel, 0), 0.5);
+    }
+}
@@ -0,0 +1,611 @@
+//! PNG decoding and encoding.
+//!
+//! Decodes every standard color type and bit depth, including palettes,
+//! `tRNS` transparency and Adam7 interlacing. 16-bit samples are reduced to
+//! 8 bits. Gray+alpha images expand to RGBA so they sample as gray on the GPU.
+
+use crate::texture::zlib::{zlib_compress, zlib_decompress};
+use crate::texture::{ImageError, PixelFormat, Texture, decoded_size};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+fn corrupt(msg: &str) -> ImageError {
+    ImageError::Corrupt(format!("png: {}", msg))
+}
+
+fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 {
+                0xedb88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        *entry = c;
+    }
+    table
+}
+
+fn crc32(table: &[u32; 256], chunks: &[&[u8]]) -> u32 {
+    let mut c = 0xffffffffu32;
+    for chunk in chunks {
+        for &b in *chunk {
+            c = table[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8);
+        }
+    }
+    c ^ 0xffffffff
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Undo the per-scanline filter in place. `prev` is the previous
+/// reconstructed row (all zeros for the first row).
+fn unfilter(filter: u8, row: &mut [u8], prev: &[u8], bpp: usize) -> Result<(), ImageError> {
+    match filter {
+        0 => {}
+        1 => {
+            for i in bpp..row.len() {
+                row[i] = row[i].wrapping_add(row[i - bpp]);
+            }
+        }
+        2 => {
+            for i in 0..row.len() {
+                row[i] = row[i].wrapping_add(prev[i]);
+            }
+        }
+        3 => {
+            for i in 0..row.len() {
+                let left = if i >= bpp { row[i - bpp] } else { 0 };
+                row[i] = row[i].wrapping_add(((left as u16 + prev[i] as u16) / 2) as u8);
+            }
+        }
+        4 => {
+            for i in 0..row.len() {
+                let left = if i >= bpp { row[i - bpp] } else { 0 };
+                let up_left = if i >= bpp { prev[i - bpp] } else { 0 };
+                row[i] = row[i].wrapping_add(paeth(left, prev[i], up_left));
+            }
+        }
+        _ => return Err(corrupt("invalid filter type")),
+    }
+    Ok(())
+}
+
+struct Header {
+    width: usize,
+    height: usize,
+    bit_depth: u8,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn samples(&self) -> usize {
+        match self.color_type {
+            0 | 3 => 1,
+            2 => 3,
+            4 => 2,
+            _ => 4,
+        }
+    }
+
+    fn bits_per_pixel(&self) -> usize {
+        self.samples() * self.bit_depth as usize
+    }
+
+    fn row_bytes(&self, width: usize) -> usize {
+        (width * self.bits_per_pixel()).div_ceil(8)
+    }
+}
+
+/// Read sample `i` of a packed scanline and scale it to 8 bits.
+fn sample(row: &[u8], i: usize, bit_depth: u8, scale: bool) -> u8 {
+    match bit_depth {
+        8 => row[i],
+        16 => row[i * 2],
+        _ => {
+            let bits = bit_depth as usize;
+            let per_byte = 8 / bits;
+            let byte = row[i / per_byte];
+            let shift = 8 - bits * (i % per_byte + 1);
+            let v = (byte >> shift) & ((1u8 << bits) - 1);
+            if scale {
+                // Replicate bits so that the maximum value maps to 255.
+                (v as u16 * 255 / ((1u16 << bits) - 1)) as u8
+            } else {
+                v
+            }
+        }
+    }
+}
+
+/// Raw 16-bit sample for `tRNS` comparison.
+fn sample16(row: &[u8], i: usize, bit_depth: u8) -> u16 {
+    if bit_depth == 16 {
+        u16::from_be_bytes([row[i * 2], row[i * 2 + 1]])
+    } else {
+        sample(row, i, bit_depth, false) as u16
+    }
+}
+
+struct Decoded<'a> {
+    header: &'a Header,
+    palette: &'a [u8],
+    trns: &'a [u8],
+    channels: usize,
+    out: Vec<u8>,
+}
+
+impl Decoded<'_> {
+    /// Expand one reconstructed scanline into output pixels.
+    /// Pixel `x` of the row lands at output column `x0 + x * dx`.
+    fn write_row(&mut self, row: &[u8], y: usize, x0: usize, dx: usize, count: usize) {
+        let h = self.header;
+        let ch = self.channels;
+        for x in 0..count {
+            let dst = (y * h.width + x0 + x * dx) * ch;
+            let px = &mut self.out[dst..dst + ch];
+            match h.color_type {
+                0 => {
+                    let g = sample(row, x, h.bit_depth, true);
+                    if ch == 1 {
+                        px[0] = g;
+                    } else {
+                        let transparent = self.trns.len() >= 2
+                            && sample16(row, x, h.bit_depth)
+                                == u16::from_be_bytes([self.trns[0], self.trns[1]]);
+                        px.copy_from_slice(&[g, g, g, if transparent { 0 } else { 255 }]);
+                    }
+                }
+                2 => {
+                    let rgb = [
+                        sample(row, x * 3, h.bit_depth, true),
+                        sample(row, x * 3 + 1, h.bit_depth, true),
+                        sample(row, x * 3 + 2, h.bit_depth, true),
+                    ];
+                    px[..3].copy_from_slice(&rgb);
+                    if ch == 4 {
+                        let transparent = self.trns.len() >= 6
+                            && (0..3).all(|c| {
+                                sample16(row, x * 3 + c, h.bit_depth)
+                                    == u16::from_be_bytes([self.trns[c * 2], self.trns[c * 2 + 1]])
+                            });
+                        px[3] = if transparent { 0 } else { 255 };
+                    }
+                }
+                3 => {
+                    let index = sample(row, x, h.bit_depth, false) as usize;
+                    let entry = self.palette.get(index * 3..index * 3 + 3);
+                    px[..3].copy_from_slice(entry.unwrap_or(&[0, 0, 0]));
+                    if ch == 4 {
+                        px[3] = *self.trns.get(index).unwrap_or(&255);
+                    }
+                }
+                4 => {
+                    let g = sample(row, x * 2, h.bit_depth, true);
+                    let a = sample(row, x * 2 + 1, h.bit_depth, true);
+                    px.copy_from_slice(&[g, g, g, a]);
+                }
+                _ => {
+                    for (c, p) in px.iter_mut().enumerate() {
+                        *p = sample(row, x * 4 + c, h.bit_depth, true);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Decode a PNG file held in memory.
+pub fn decode(bytes: &[u8]) -> Result<Texture, ImageError> {
+    if bytes.len() < 8 || bytes[..8] != SIGNATURE {
+        return Err(corrupt("missing signature"));
+    }
+    let table = crc_table();
+    let mut pos = 8;
+    let mut header: Option<Header> = None;
+    let mut palette: Vec<u8> = Vec::new();
+    let mut trns: Vec<u8> = Vec::new();
+    let mut idat: Vec<u8> = Vec::new();
+
+    loop {
+        let chunk_header = bytes
+            .get(pos..pos + 8)
+            .ok_or_else(|| corrupt("truncated chunk header"))?;
+        let len = u32::from_be_bytes(chunk_header[..4].try_into().unwrap()) as usize;
+        let kind = &chunk_header[4..8];
+        let data = bytes
+            .get(pos + 8..pos + 8 + len)
+            .ok_or_else(|| corrupt("truncated chunk data"))?;
+        let crc = bytes
+            .get(pos + 8 + len..pos + 12 + len)
+            .ok_or_else(|| corrupt("truncated chunk crc"))?;
+        if crc32(&table, &[kind, data]) != u32::from_be_bytes(crc.try_into().unwrap()) {
+            return Err(corrupt(&format!(
+                "crc mismatch in {} chunk",
+                String::from_utf8_lossy(kind)
+            )));
+        }
+        pos += 12 + len;
+
+        match kind {
+            b"IHDR" => {
+                if len != 13 {
+                    return Err(corrupt("IHDR has wrong length"));
+                }
+                let h = Header {
+                    width: u32::from_be_bytes(data[0..4].try_into().unwrap()) as usize,
+                    height: u32::from_be_bytes(data[4..8].try_into().unwrap()) as usize,
+                    bit_depth: data[8],
+                    color_type: data[9],
+                    interlaced: data[12] == 1,
+                };
+                let valid_depth = match h.color_type {
+                    0 => matches!(h.bit_depth, 1 | 2 | 4 | 8 | 16),
+                    3 => matches!(h.bit_depth, 1 | 2 | 4 | 8),
+                    2 | 4 | 6 => matches!(h.bit_depth, 8 | 16),
+                    _ => {
+                        return Err(ImageError::Unsupported(format!(
+                            "png: color type {}",
+                            h.color_type
+                        )));
+                    }
+                };
+                if !valid_depth {
+                    return Err(corrupt(&format!(
+                        "bit depth {} is invalid for color type {}",
+                        h.bit_depth, h.color_type
+                    )));
+                }
+                // Decoded images have up to 4 channels.
+                if decoded_size(h.width, h.height, 4).is_none() {
+                    return Err(corrupt("invalid image dimensions"));
+                }
+                if data[10] != 0 || data[11] != 0 || data[12] > 1 {
+                    return Err(ImageError::Unsupported(
+                        "png: unknown compression, filter or interlace method".to_string(),
+                    ));
+                }
+                header = Some(h);
+            }
+            b"PLTE" => palette = data.to_vec(),
+            b"tRNS" => trns = data.to_vec(),
+            b"IDAT" => idat.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {
+                // Bit 5 of the first byte clear means the chunk is critical.
+                if kind[0] & 0x20 == 0 {
+                    return Err(ImageError::Unsupported(format!(
+                        "png: critical chunk {}",
+                        String::from_utf8_lossy(kind)
+                    )));
+                }
+            }
+        }
+    }
+
+    let header = header.ok_or_else(|| corrupt("missing IHDR chunk"))?;
+    if header.color_type == 3 && palette.is_empty() {
+        return Err(corrupt("missing PLTE chunk"));
+    }
+
+    let channels = match header.color_type {
+        0 if trns.is_empty() => 1,
+        2 | 3 if trns.is_empty() => 3,
+        _ => 4,
+    };
+
+    // (x0, y0, dx, dy) for each pass; a single full pass when not interlaced.
+    let passes: &[(usize, usize, usize, usize)] = if header.interlaced {
+        &[
+            (0, 0, 8, 8),
+            (4, 0, 8, 8),
+            (0, 4, 4, 8),
+            (2, 0, 4, 4),
+            (0, 2, 2, 4),
+            (1, 0, 2, 2),
+            (0, 1, 1, 2),
+        ]
+    } else {
+        &[(0, 0, 1, 1)]
+    };
+    // (width, height) of each pass, zero when the image is too small for it.
+    let pass_sizes: Vec<_> = passes
+        .iter()
+        .map(|&(x0, y0, dx, dy)| {
+            let w = header.width.saturating_sub(x0).div_ceil(dx);
+            let h = header.height.saturating_sub(y0).div_ceil(dy);
+            (w, h)
+        })
+        .collect();
+    // Size of the filtered scanlines, checked before allocating the image.
+    let expected: usize = pass_sizes
+        .iter()
+        .filter(|&&(w, h)| w > 0 && h > 0)
+        .map(|&(w, h)| h * (1 + header.row_bytes(w)))
+        .sum();
+    // Inflating stops there, so a small IDAT cannot expand without bound.
+    let raw = zlib_decompress(&idat, expected)?;
+    if raw.len() < expected {
+        return Err(corrupt("image data too short"));
+    }
+
+    let mut decoded = Decoded {
+        header: &header,
+        palette: &palette,
+        trns: &trns,
+        channels,
+        out: vec![0; header.width * header.height * channels],
+    };
+
+    let bpp = header.bits_per_pixel().div_ceil(8);
+    let mut offset = 0;
+    for (&(x0, y0, dx, dy), &(pass_w, pass_h)) in passes.iter().zip(&pass_sizes) {
+        if pass_w == 0 || pass_h == 0 {
+            continue;
+        }
+        let row_len = header.row_bytes(pass_w);
+        let mut prev = vec![0u8; row_len];
+        for py in 0..pass_h {
+            let filter = raw[offset];
+            let mut row = raw[offset + 1..offset + 1 + row_len].to_vec();
+            offset += 1 + row_len;
+            unfilter(filter, &mut row, &prev, bpp)?;
+            decoded.write_row(&row, y0 + py * dy, x0, dx, pass_w);
+            prev = row;
+        }
+    }
+
+    Ok(Texture {
+        width: header.width as i32,
+        height: header.height as i32,
+        format: PixelFormat::from_channels(channels).unwrap(),
+        data: decoded.out,
+    })
+}
+
+fn write_chunk(out: &mut Vec<u8>, table: &[u32; 256], kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    out.extend_from_slice(&crc32(table, &[kind, data]).to_be_bytes());
+}
+
+/// Encode as an 8-bit gray, gray + alpha, RGB or RGBA PNG depending on the format.
+pub fn encode(texture: &Texture) -> Result<Vec<u8>, ImageError> {
+    let color_type = match texture.format {
+        PixelFormat::R8 => 0,
+        PixelFormat::RG8 => 4,
+        PixelFormat::RGB8 => 2,
+        PixelFormat::RGBA8 => 6,
+        f => {
+            return Err(ImageError::Unsupported(format!(
+                "png: cannot encode {:?}, convert to an 8-bit format first",
+                f
+            )));
+        }
+    };
+    let bpp = texture.format.bytes_per_pixel();
+    let row_len = texture.width as usize * bpp;
+    if texture.data.len() < row_len * texture.height as usize {
+        return Err(ImageError::InvalidTexture(
+            "data is smaller than width * height * channels".to_string(),
+        ));
+    }
+
+    // Pick the filter with the smallest sum of absolute residuals per row.
+    let mut filtered = Vec::with_capacity((row_len + 1) * texture.height as usize);
+    let zero_row = vec![0u8; row_len];
+    let mut candidate = vec![0u8; row_len];
+    let mut best = vec![0u8; row_len];
+    for y in 0..texture.height as usize {
+        let row = &texture.data[y * row_len..(y + 1) * row_len];
+        let prev = if y == 0 {
+            &zero_row[..]
+        } else {
+            &texture.data[(y - 1) * row_len..y * row_len]
+        };
+        let mut best_filter = 0;
+        let mut best_score = u64::MAX;
+        for filter in 0..5u8 {
+            for i in 0..row_len {
+                let left = if i >= bpp { row[i - bpp] } else { 0 };
+                let up_left = if i >= bpp { prev[i - bpp] } else { 0 };
+                let predicted = match filter {
+                    0 => 0,
+                    1 => left,
+                    2 => prev[i],
+                    3 => ((left as u16 + prev[i] as u16) / 2) as u8,
+                    _ => paeth(left, prev[i], up_left),
+                };
+                candidate[i] = row[i].wrapping_sub(predicted);
+            }
+            let score: u64 = candidate
+                .iter()
+                .map(|&v| (v as i8).unsigned_abs() as u64)
+                .sum();
+            if score < best_score {
+                best_score = score;
+                best_filter = filter;
+                best.copy_from_slice(&candidate);
+            }
+        }
+        filtered.push(best_filter);
+        filtered.extend_from_slice(&best);
+    }
+
+    Ok(assemble(
+        texture.width as u32,
+        texture.height as u32,
+        8,
+        color_type,
+        &[],
+        &filtered,
+    ))
+}
+
+/// Wrap filtered scanlines and any chunks that precede IDAT into a PNG file.
+fn assemble(
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    chunks: &[(&[u8; 4], Vec<u8>)],
+    filtered: &[u8],
+) -> Vec<u8> {
+    let table = crc_table();
+    let mut out = SIGNATURE.to_vec();
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    ihdr.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+    write_chunk(&mut out, &table, b"IHDR", &ihdr);
+    for (kind, data) in chunks {
+        write_chunk(&mut out, &table, kind, data);
+    }
+    write_chunk(&mut out, &table, b"IDAT", &zlib_compress(filtered));
+    write_chunk(&mut out, &table, b"IEND", &[]);
+    out
+}
+
+/// Encode a palette image. The bit depth is the smallest of 1, 2, 4 or 8
+/// that holds every palette index; alpha is written as a `tRNS` chunk when
+/// any entry is not opaque.
+pub fn encode_indexed(
+    width: u32,
+    height: u32,
+    palette: &[[u8; 4]],
+    indices: &[u8],
+) -> Result<Vec<u8>, ImageError> {
+    if palette.is_empty() || palette.len() > 256 {
+        return Err(ImageError::InvalidTexture(format!(
+            "palette has {} entries, expected 1 to 256",
+            palette.len()
+        )));
+    }
+    let (w, h) = (width as usize, height as usize);
+    if indices.len() < w * h {
+        return Err(ImageError::InvalidTexture(
+            "fewer indices than width * height".to_string(),
+        ));
+    }
+    if indices[..w * h]
+        .iter()
+        .any(|&i| i as usize >= palette.len())
+    {
+        return Err(ImageError::InvalidTexture(
+            "palette index out of range".to_string(),
+        ));
+    }
+    let bit_depth: u8 = match palette.len() {
+        0..=2 => 1,
+        3..=4 => 2,
+        5..=16 => 4,
+        _ => 8,
+    };
+
+    // Pack each row most significant bits first; palette images compress
+    // best without prediction filters.
+    let per_byte = 8 / bit_depth as usize;
+    let row_len = w.div_ceil(per_byte);
+    let mut filtered = Vec::with_capacity((row_len + 1) * h);
+    for row in indices[..w * h].chunks_exact(w.max(1)).take(h) {
+        filtered.push(0);
+        for group in row.chunks(per_byte) {
+            let mut byte = 0u8;
+            for (k, &i) in group.iter().enumerate() {
+                byte |= i << (8 - bit_depth as usize * (k + 1));
+            }
+            filtered.push(byte);
+        }
+    }
+
+    let plte: Vec<u8> = palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect();
+    let mut chunks = vec![(b"PLTE", plte)];
+    // tRNS may stop after the last non-opaque entry.
+    if let Some(last) = palette.iter().rposition(|c| c[3] != 255) {
+        chunks.push((b"tRNS", palette[..=last].iter().map(|c| c[3]).collect()));
+    }
+    Ok(assemble(width, height, bit_depth, 3, &chunks, &filtered))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(format: PixelFormat) -> Texture {
+        let mut texture = Texture::new(5, 3, format);
+        for (i, d) in texture.data.iter_mut().enumerate() {
+            *d = (i * 37) as u8;
+        }
+        texture
+    }
+
+    #[test]
+    fn round_trips_8bit_formats() {
+        for format in [PixelFormat::R8, PixelFormat::RGB8, PixelFormat::RGBA8] {
+            let texture = gradient(format);
+            let decoded = decode(&encode(&texture).unwrap()).unwrap();
+            assert_eq!((decoded.width, decoded.height), (5, 3));
+            assert_eq!(decoded.format, format);
+            assert_eq!(decoded.data, texture.data);
+        }
+    }
+
+    #[test]
+    fn expands_gray_alpha_to_rgba() {
+        let texture = gradient(PixelFormat::RG8);
+        let decoded = decode(&encode(&texture).unwrap()).unwrap();
+        assert_eq!(decoded.format, PixelFormat::RGBA8);
+        assert_eq!(decoded.data, texture.convert(PixelFormat::RGBA8).data);
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        let bytes = encode(&gradient(PixelFormat::RGBA8)).unwrap();
+        assert!(decode(&bytes[..bytes.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn rejects_crc_mismatch() {
+        let mut bytes = encode(&gradient(PixelFormat::RGB8)).unwrap();
+        // First byte of the IHDR data.
+        bytes[16] ^= 1;
+        assert!(matches!(decode(&bytes), Err(ImageError::Corrupt(_))));
+    }
+
+    #[test]
+    fn rejects_oversized_dimensions() {
+        let bytes = assemble(1, u32::MAX, 8, 0, &[], &[0, 0]);
+        assert!(matches!(decode(&bytes), Err(ImageError::Corrupt(_))));
+    }
+
+    #[test]
+    fn rejects_short_image_data() {
+        let bytes = assemble(4, 4, 8, 6, &[], &[0; 10]);
+        assert!(matches!(decode(&bytes), Err(ImageError::Corrupt(_))));
+    }
+
+    #[test]
+    fn stops_inflating_past_the_image_size() {
+        // A few kilobytes of IDAT that would inflate to 2 MB.
+        let bytes = assemble(4, 4, 8, 6, &[], &vec![0; 2_000_000]);
+        assert!(bytes.len() < 100_000);
+        assert!(matches!(decode(&bytes), Err(ImageError::Corrupt(_))));
+    }
+}
@@ -0,0 +1,181 @@
+//! Netpbm decoding and encoding.
+//!
+//! Decodes ASCII and binary graymaps and pixmaps (P2, P3, P5, P6) with any
+//! maxval up to 65535. Encodes P5 for gray formats and P6 for color; PPM has
+//! no alpha, so the alpha channel of `RG8` and `RGBA8` textures is dropped.
+
+use crate::texture::{ImageError, PixelFormat, Texture, decoded_size};
+
+fn corrupt(msg: &str) -> ImageError {
+    ImageError::Corrupt(format!("ppm: {}", msg))
+}
+
+struct Tokens<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Tokens<'_> {
+    /// Next whitespace-separated unsigned integer, skipping `#` comments.
+    fn number(&mut self) -> Result<u32, ImageError> {
+        loop {
+            match self.bytes.get(self.pos) {
+                Some(b'#') => {
+                    while self.bytes.get(self.pos).is_some_and(|&b| b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                Some(_) => break,
+                None => return Err(corrupt("unexpected end of file")),
+            }
+        }
+        let start = self.pos;
+        while self.bytes.get(self.pos).is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| corrupt("expected a number"))
+    }
+}
+
+/// Decode a PGM or PPM file held in memory.
+pub fn decode(bytes: &[u8]) -> Result<Texture, ImageError> {
+    if bytes.len() < 2 || bytes[0] != b'P' {
+        return Err(corrupt("missing magic number"));
+    }
+    let (format, ascii) = match bytes[1] {
+        b'2' => (PixelFormat::R8, true),
+        b'3' => (PixelFormat::RGB8, true),
+        b'5' => (PixelFormat::R8, false),
+        b'6' => (PixelFormat::RGB8, false),
+        m => {
+            return Err(ImageError::Unsupported(format!(
+                "ppm: format P{}",
+                m as char
+            )));
+        }
+    };
+
+    let mut tokens = Tokens { bytes, pos: 2 };
+    let width = tokens.number()? as usize;
+    let height = tokens.number()? as usize;
+    let maxval = tokens.number()?;
+    let count = decoded_size(width, height, format.channels())
+        .ok_or_else(|| corrupt("invalid image dimensions"))?;
+    if maxval == 0 || maxval > 65535 {
+        return Err(corrupt("maxval must be between 1 and 65535"));
+    }
+
+    let scale = |v: u32| -> u8 { (v.min(maxval) * 255 / maxval) as u8 };
+    let sample_bytes = if maxval > 255 { 2 } else { 1 };
+    // Exactly one whitespace byte separates the header from the raster.
+    // ASCII samples take at least one byte each.
+    let start = tokens.pos + 1;
+    let needed = if ascii { count } else { count * sample_bytes };
+    if bytes.len().saturating_sub(start) < needed {
+        return Err(corrupt("truncated pixel data"));
+    }
+    let mut texture = Texture::new(width as i32, height as i32, format);
+    if ascii {
+        for d in texture.data.iter_mut() {
+            *d = scale(tokens.number()?);
+        }
+    } else {
+        let raster = &bytes[start..start + count * sample_bytes];
+        for (d, s) in texture.data.iter_mut().zip(raster.chunks(sample_bytes)) {
+            let v = if sample_bytes == 2 {
+                u16::from_be_bytes([s[0], s[1]]) as u32
+            } else {
+                s[0] as u32
+            };
+            *d = scale(v);
+        }
+    }
+    Ok(texture)
+}
+
+/// Encode as binary PGM (`R8`, `RG8`) or PPM (`RGB8`, `RGBA8`). Alpha is dropped.
+pub fn encode(texture: &Texture) -> Result<Vec<u8>, ImageError> {
+    let (magic, kept) = match texture.format {
+        PixelFormat::R8 | PixelFormat::RG8 => ("P5", 1),
+        PixelFormat::RGB8 | PixelFormat::RGBA8 => ("P6", 3),
+        f => {
+            return Err(ImageError::Unsupported(format!(
+                "ppm: cannot encode {:?}, convert to an 8-bit format first",
+                f
+            )));
+        }
+    };
+    let channels = texture.channels();
+    let count = texture.width.max(0) as usize * texture.height.max(0) as usize;
+    if texture.data.len() < count * channels {
+        return Err(ImageError::InvalidTexture(
+            "data is smaller than width * height * channels".to_string(),
+        ));
+    }
+    let mut out = format!("{}\n{} {}\n255\n", magic, texture.width, texture.height).into_bytes();
+    for px in texture.data[..count * channels].chunks(channels) {
+        out.extend_from_slice(&px[..kept]);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_gray_and_color() {
+        for format in [PixelFormat::R8, PixelFormat::RGB8] {
+            let mut texture = Texture::new(5, 3, format);
+            for (i, d) in texture.data.iter_mut().enumerate() {
+                *d = (i * 37) as u8;
+            }
+            let decoded = decode(&encode(&texture).unwrap()).unwrap();
+            assert_eq!((decoded.width, decoded.height), (5, 3));
+            assert_eq!(decoded.format, format);
+            assert_eq!(decoded.data, texture.data);
+        }
+    }
+
+    #[test]
+    fn decodes_ascii_with_comments_and_maxval() {
+        let texture = decode(b"P2\n# comment\n3 1\n15\n0 15 5\n").unwrap();
+        assert_eq!(texture.format, PixelFormat::R8);
+        assert_eq!(texture.data, [0, 255, 85]);
+    }
+
+    #[test]
+    fn decodes_16_bit_samples() {
+        let texture = decode(b"P5 2 1 65535\n\xff\xff\x80\x00").unwrap();
+        assert_eq!(texture.data, [255, 127]);
+    }
+
+    #[test]
+    fn rejects_truncated_pixel_data() {
+        assert!(matches!(
+            decode(b"P6 2 2 255\n\x01\x02\x03"),
+            Err(ImageError::Corrupt(_))
+        ));
+        assert!(matches!(
+            decode(b"P2 2 2 255\n1 2 3"),
+            Err(ImageError::Corrupt(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        for bytes in [
+            &b"P5 1 4294967295 255\n"[..],
+            b"P5 0 1 255\n",
+            b"P5 1 1 0\n\x00",
+            b"P5 1 x 255\n",
+        ] {
+            assert!(matches!(decode(bytes), Err(ImageError::Corrupt(_))));
+        }
+        assert!(matches!(decode(b"P7 1 1"), Err(ImageError::Unsupported(_))));
+    }
+}
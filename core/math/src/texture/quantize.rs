@@ -0,0 +1,374 @@
+//! Color quantization, dithering and indexed palette images.
+//!
+//! Quantization works on 8-bit RGB. Alpha is reduced to one bit: pixels with
+//! alpha below 128 map to a single transparent palette entry, everything
+//! else is treated as opaque.
+
+use crate::texture::png;
+use crate::texture::{ImageError, PixelFormat, Texture};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum QuantizeMethod {
+    /// Recursively split the color box with the widest range at its median.
+    #[default]
+    MedianCut,
+    /// Build an 8-level color octree and merge the deepest leaves. Faster,
+    /// but merging whole nodes can leave fewer colors than requested.
+    Octree,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Dither {
+    #[default]
+    None,
+    /// Error diffusion; best for photographs.
+    FloydSteinberg,
+    /// 8×8 Bayer threshold matrix; stable, patterned look suited to pixel art.
+    Ordered,
+}
+
+/// An image stored as palette indices.
+#[derive(Clone, Debug)]
+pub struct IndexedImage {
+    pub width: i32,
+    pub height: i32,
+    /// RGBA entries, at most 256.
+    pub palette: Vec<[u8; 4]>,
+    /// One palette index per pixel, row-major.
+    pub indices: Vec<u8>,
+}
+
+impl IndexedImage {
+    /// Expand back to an `RGBA8` texture.
+    pub fn to_texture(&self) -> Texture {
+        let mut out = Texture::new(self.width, self.height, PixelFormat::RGBA8);
+        for (px, &i) in out.data.chunks_exact_mut(4).zip(&self.indices) {
+            px.copy_from_slice(self.palette.get(i as usize).unwrap_or(&[0; 4]));
+        }
+        out
+    }
+
+    /// Encode as a palette PNG with the smallest bit depth that fits.
+    pub fn encode_png(&self) -> Result<Vec<u8>, ImageError> {
+        png::encode_indexed(
+            self.width.max(0) as u32,
+            self.height.max(0) as u32,
+            &self.palette,
+            &self.indices,
+        )
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), ImageError> {
+        std::fs::write(path, self.encode_png()?)?;
+        Ok(())
+    }
+}
+
+/// Unique opaque colors and how often they occur.
+fn histogram(rgba: &[u8]) -> Vec<([u8; 3], u32)> {
+    let mut counts: HashMap<[u8; 3], u32> = HashMap::new();
+    for px in rgba.chunks_exact(4).filter(|px| px[3] >= 128) {
+        *counts.entry([px[0], px[1], px[2]]).or_insert(0) += 1;
+    }
+    let mut colors: Vec<_> = counts.into_iter().collect();
+    // Deterministic output regardless of hash order.
+    colors.sort_unstable();
+    colors
+}
+
+fn weighted_mean(colors: &[([u8; 3], u32)]) -> [u8; 3] {
+    let mut sum = [0u64; 3];
+    let mut total = 0u64;
+    for (c, n) in colors {
+        for k in 0..3 {
+            sum[k] += c[k] as u64 * *n as u64;
+        }
+        total += *n as u64;
+    }
+    let total = total.max(1);
+    [0, 1, 2].map(|k| ((sum[k] + total / 2) / total) as u8)
+}
+
+fn median_cut(mut colors: Vec<([u8; 3], u32)>, n: usize) -> Vec<[u8; 3]> {
+    if colors.is_empty() || n == 0 {
+        return vec![];
+    }
+    let range = |b: &[([u8; 3], u32)]| -> (usize, u8) {
+        (0..3)
+            .map(|k| {
+                let lo = b.iter().map(|c| c.0[k]).min().unwrap_or(0);
+                let hi = b.iter().map(|c| c.0[k]).max().unwrap_or(0);
+                (k, hi - lo)
+            })
+            .max_by_key(|&(_, r)| r)
+            .unwrap()
+    };
+
+    // Boxes are contiguous ranges of `colors`.
+    let mut boxes = vec![Range {
+        start: 0,
+        end: colors.len(),
+    }];
+    while boxes.len() < n {
+        let Some((i, axis)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (axis, r) = range(&colors[b.clone()]);
+                let pixels: u64 = colors[b.clone()].iter().map(|c| c.1 as u64).sum();
+                (i, axis, r as u64 * pixels)
+            })
+            .max_by_key(|&(_, _, score)| score)
+            .filter(|&(_, _, score)| score > 0)
+            .map(|(i, axis, _)| (i, axis))
+        else {
+            break;
+        };
+        let b = boxes[i].clone();
+        let slice = &mut colors[b.clone()];
+        slice.sort_unstable_by_key(|c| c.0[axis]);
+        // Split where half of the pixels fall on each side.
+        let total: u64 = slice.iter().map(|c| c.1 as u64).sum();
+        let mut acc = 0;
+        let mut split = 1;
+        for (j, c) in slice.iter().enumerate() {
+            acc += c.1 as u64;
+            if acc * 2 >= total {
+                split = (j + 1).clamp(1, slice.len() - 1);
+                break;
+            }
+        }
+        boxes[i] = b.start..b.start + split;
+        boxes.push(b.start + split..b.end);
+    }
+    boxes
+        .into_iter()
+        .map(|b| weighted_mean(&colors[b]))
+        .collect()
+}
+
+#[derive(Clone, Default)]
+struct OctreeNode {
+    children: [u32; 8],
+    sum: [u64; 3],
+    count: u64,
+    leaf: bool,
+}
+
+fn octree(colors: &[([u8; 3], u32)], n: usize) -> Vec<[u8; 3]> {
+    if colors.is_empty() || n == 0 {
+        return vec![];
+    }
+    // Node 0 is the root; child index 0 means "no child".
+    let mut nodes = vec![OctreeNode::default()];
+    let mut levels: Vec<Vec<u32>> = vec![vec![]; 8];
+    let mut leaves = 0usize;
+    for &(c, count) in colors {
+        // Every node keeps the totals of its whole subtree.
+        let mut node = 0usize;
+        for depth in 0..=8 {
+            for (sum, &v) in nodes[node].sum.iter_mut().zip(&c) {
+                *sum += v as u64 * count as u64;
+            }
+            nodes[node].count += count as u64;
+            if depth == 8 {
+                break;
+            }
+            let bit = 7 - depth;
+            let slot =
+                ((c[0] >> bit & 1) << 2 | (c[1] >> bit & 1) << 1 | (c[2] >> bit & 1)) as usize;
+            if nodes[node].children[slot] == 0 {
+                nodes.push(OctreeNode {
+                    leaf: depth == 7,
+                    ..Default::default()
+                });
+                let id = nodes.len() as u32 - 1;
+                nodes[node].children[slot] = id;
+                if depth == 7 {
+                    leaves += 1;
+                } else {
+                    levels[depth + 1].push(id);
+                }
+            }
+            node = nodes[node].children[slot] as usize;
+        }
+    }
+    levels[0].push(0);
+
+    // Fold the deepest inner nodes into leaves until few enough remain,
+    // least populated first so common colors keep their precision.
+    for depth in (0..8).rev() {
+        let level = &mut levels[depth];
+        level.sort_by_key(|&id| std::cmp::Reverse(nodes[id as usize].count));
+        while leaves > n {
+            let Some(id) = level.pop() else {
+                break;
+            };
+            let node = &mut nodes[id as usize];
+            let merged = node.children.iter().filter(|&&c| c != 0).count();
+            node.children = [0; 8];
+            node.leaf = true;
+            leaves = leaves + 1 - merged;
+        }
+    }
+
+    let mut palette = vec![];
+    let mut stack = vec![0usize];
+    while let Some(id) = stack.pop() {
+        let node = &nodes[id];
+        if node.leaf {
+            let total = node.count.max(1);
+            palette.push([0, 1, 2].map(|k| ((node.sum[k] + total / 2) / total) as u8));
+        } else {
+            stack.extend(
+                node.children
+                    .iter()
+                    .filter(|&&c| c != 0)
+                    .map(|&c| c as usize),
+            );
+        }
+    }
+    palette
+}
+
+/// Maps colors to their nearest palette entry, caching repeated lookups.
+struct Matcher<'a> {
+    palette: &'a [[u8; 4]],
+    cache: HashMap<[u8; 3], u8>,
+}
+
+impl Matcher<'_> {
+    fn nearest(&mut self, c: [u8; 3]) -> u8 {
+        if let Some(&i) = self.cache.get(&c) {
+            return i;
+        }
+        let dist =
+            |p: &[u8; 4]| -> i32 { (0..3).map(|k| (p[k] as i32 - c[k] as i32).pow(2)).sum() };
+        let i = self
+            .palette
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p[3] != 0)
+            .min_by_key(|(_, p)| dist(p))
+            .map_or(0, |(i, _)| i as u8);
+        self.cache.insert(c, i);
+        i
+    }
+}
+
+const BAYER8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+impl Texture {
+    /// Choose a palette of at most `colors` entries (clamped to 1..=256)
+    /// for this texture. A transparent entry is appended, within the limit,
+    /// when any pixel has alpha below 128.
+    pub fn palette(&self, colors: usize, method: QuantizeMethod) -> Vec<[u8; 4]> {
+        let rgba = self.convert(PixelFormat::RGBA8);
+        let colors = colors.clamp(1, 256);
+        let transparent = rgba.data.chunks_exact(4).any(|px| px[3] < 128);
+        let opaque = colors - (transparent && colors > 1) as usize;
+        let hist = histogram(&rgba.data);
+        let rgb = match method {
+            QuantizeMethod::MedianCut => median_cut(hist, opaque),
+            QuantizeMethod::Octree => octree(&hist, opaque),
+        };
+        let mut palette: Vec<[u8; 4]> = rgb.into_iter().map(|c| [c[0], c[1], c[2], 255]).collect();
+        if transparent && palette.len() < colors {
+            palette.push([0, 0, 0, 0]);
+        }
+        palette
+    }
+
+    /// Reduce to at most `colors` colors and return the indexed result.
+    pub fn quantize(&self, colors: usize, method: QuantizeMethod, dither: Dither) -> IndexedImage {
+        let palette = self.palette(colors, method);
+        self.remap(&palette, dither)
+    }
+
+    /// Map every pixel to its nearest entry of a fixed `palette`, e.g. a
+    /// console palette. Transparent pixels use the first entry with zero
+    /// alpha, or the nearest color if there is none.
+    pub fn remap(&self, palette: &[[u8; 4]], dither: Dither) -> IndexedImage {
+        let rgba = self.convert(PixelFormat::RGBA8);
+        let (w, h) = (rgba.width.max(0) as usize, rgba.height.max(0) as usize);
+        let mut indices = vec![0u8; w * h];
+        let palette = &palette[..palette.len().min(256)];
+        if palette.is_empty() {
+            return IndexedImage {
+                width: rgba.width,
+                height: rgba.height,
+                palette: vec![],
+                indices,
+            };
+        }
+        let clear = palette.iter().position(|p| p[3] == 0);
+        let mut matcher = Matcher {
+            palette,
+            cache: HashMap::new(),
+        };
+        // Diffused error for the current and next row, padded by one pixel each side.
+        let mut cur = vec![[0f32; 3]; w + 2];
+        let mut next = vec![[0f32; 3]; w + 2];
+        let spread = 255.0 / (palette.len() as f32).cbrt();
+
+        for y in 0..h {
+            for x in 0..w {
+                let px = &rgba.data[(y * w + x) * 4..(y * w + x) * 4 + 4];
+                if px[3] < 128
+                    && let Some(c) = clear
+                {
+                    indices[y * w + x] = c as u8;
+                    continue;
+                }
+                let mut c = [px[0] as f32, px[1] as f32, px[2] as f32];
+                match dither {
+                    Dither::None => {}
+                    Dither::FloydSteinberg => {
+                        for k in 0..3 {
+                            c[k] += cur[x + 1][k];
+                        }
+                    }
+                    Dither::Ordered => {
+                        let t = (BAYER8[y % 8][x % 8] as f32 + 0.5) / 64.0 - 0.5;
+                        c.iter_mut().for_each(|v| *v += t * spread);
+                    }
+                }
+                let q = c.map(|v| v.round().clamp(0.0, 255.0) as u8);
+                let i = matcher.nearest(q);
+                indices[y * w + x] = i;
+
+                if dither == Dither::FloydSteinberg {
+                    let p = palette[i as usize];
+                    for k in 0..3 {
+                        let e = c[k] - p[k] as f32;
+                        cur[x + 2][k] += e * 7.0 / 16.0;
+                        next[x][k] += e * 3.0 / 16.0;
+                        next[x + 1][k] += e * 5.0 / 16.0;
+                        next[x + 2][k] += e / 16.0;
+                    }
+                }
+            }
+            std::mem::swap(&mut cur, &mut next);
+            next.fill([0.0; 3]);
+        }
+        IndexedImage {
+            width: rgba.width,
+            height: rgba.height,
+            palette: palette.to_vec(),
+            indices,
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Software triangle rasterizer following the OpenGL pipeline conventions.
+//!
+//! Vertices are given in clip space, as a vertex shader would output them,
+//! and carry `N` varyings that are interpolated perspective-correctly and
+//! passed to a fragment closure. NDC y points up, so NDC (-1, 1) lands on
+//! the top-left corner of row 0 and the target reads like the screen.
+//! Depth uses the default [0, 1] range with a `LESS` test, and
+//! counter-clockwise triangles are front-facing. Pixel centers are sampled
+//! with the top-left fill rule, so shared edges are drawn exactly once.
+
+use crate::Color;
+use crate::texture::blend::{BlendMode, blend_rgba};
+use crate::texture::{PixelFormat, Texture};
+
+/// A vertex after the vertex stage: clip-space position and varyings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClipVertex<const N: usize> {
+    pub position: [f32; 4],
+    pub varyings: [f32; N],
+}
+
+/// Which faces are discarded before rasterization.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CullMode {
+    #[default]
+    None,
+    Back,
+    Front,
+}
+
+/// Clip planes as (coefficients, offset): a vertex is kept when
+/// `dot(coefficients, position) >= offset`. x and y are clipped against a
+/// guard band rather than the viewport; the rest is cut by the scissoring
+/// in `fill_triangle`.
+const GUARD_BAND: f32 = 4.0;
+const CLIP_PLANES: [([f32; 4], f32); 7] = [
+    ([0.0, 0.0, 0.0, 1.0], 1e-6),
+    ([0.0, 0.0, 1.0, 1.0], 0.0),
+    ([0.0, 0.0, -1.0, 1.0], 0.0),
+    ([1.0, 0.0, 0.0, GUARD_BAND], 0.0),
+    ([-1.0, 0.0, 0.0, GUARD_BAND], 0.0),
+    ([0.0, 1.0, 0.0, GUARD_BAND], 0.0),
+    ([0.0, -1.0, 0.0, GUARD_BAND], 0.0),
+];
+
+fn lerp_vertex<const N: usize>(a: &ClipVertex<N>, b: &ClipVertex<N>, t: f32) -> ClipVertex<N> {
+    ClipVertex {
+        position: std::array::from_fn(|i| a.position[i] + (b.position[i] - a.position[i]) * t),
+        varyings: std::array::from_fn(|i| a.varyings[i] + (b.varyings[i] - a.varyings[i]) * t),
+    }
+}
+
+/// Sutherland-Hodgman clipping of a convex polygon in clip space.
+fn clip_polygon<const N: usize>(poly: &mut Vec<ClipVertex<N>>, scratch: &mut Vec<ClipVertex<N>>) {
+    for (plane, offset) in CLIP_PLANES {
+        let dist = |v: &ClipVertex<N>| {
+            plane
+                .iter()
+                .zip(&v.position)
+                .map(|(p, c)| p * c)
+                .sum::<f32>()
+                - offset
+        };
+        if poly.iter().all(|v| dist(v) >= 0.0) {
+            continue;
+        }
+        scratch.clear();
+        for (i, a) in poly.iter().enumerate() {
+            let b = &poly[(i + 1) % poly.len()];
+            let (da, db) = (dist(a), dist(b));
+            if da >= 0.0 {
+                scratch.push(*a);
+            }
+            if (da >= 0.0) != (db >= 0.0) {
+                scratch.push(lerp_vertex(a, b, da / (da - db)));
+            }
+        }
+        std::mem::swap(poly, scratch);
+        if poly.len() < 3 {
+            poly.clear();
+            return;
+        }
+    }
+}
+
+/// A vertex in window space. Varyings are stored divided by w so they can
+/// be interpolated linearly and corrected per pixel.
+struct ScreenVertex<const N: usize> {
+    x: f32,
+    y: f32,
+    z: f32,
+    inv_w: f32,
+    varyings: [f32; N],
+}
+
+fn edge(ax: f32, ay: f32, bx: f32, by: f32, px: f32, py: f32) -> f32 {
+    (bx - ax) * (py - ay) - (by - ay) * (px - ax)
+}
+
+/// Whether a pixel center exactly on the edge from `a` to `b` belongs to
+/// the triangle, for triangles with positive area.
+fn is_top_left<const N: usize>(a: &ScreenVertex<N>, b: &ScreenVertex<N>) -> bool {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    (dy == 0.0 && dx > 0.0) || dy < 0.0
+}
+
+/// A color target with a depth buffer and the fixed-function state that
+/// applies to each draw.
+#[derive(Clone, Debug)]
+pub struct Rasterizer {
+    pub target: Texture,
+    /// One value per pixel in [0, 1], row-major like `target`.
+    pub depth: Vec<f32>,
+    pub depth_test: bool,
+    pub cull: CullMode,
+    /// `None` overwrites the target with the fragment color.
+    pub blend: Option<BlendMode>,
+}
+
+impl Rasterizer {
+    /// A cleared target with the depth test, culling and blending disabled.
+    pub fn new(width: i32, height: i32, format: PixelFormat) -> Self {
+        let (width, height) = (width.max(0), height.max(0));
+        Rasterizer {
+            target: Texture::new(width, height, format),
+            depth: vec![1.0; width as usize * height as usize],
+            depth_test: false,
+            cull: CullMode::None,
+            blend: None,
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.target.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.target.height
+    }
+
+    pub fn clear_color(&mut self, color: Color) {
+        let format = self.target.format;
+        let mut pixel = vec![0u8; format.bytes_per_pixel()];
+        format.write_rgba(&mut pixel, [color.r, color.g, color.b, color.a]);
+        for chunk in self.target.data.chunks_exact_mut(pixel.len()) {
+            chunk.copy_from_slice(&pixel);
+        }
+    }
+
+    pub fn clear_depth(&mut self) {
+        self.depth.fill(1.0);
+    }
+
+    /// Draw a triangle list; a trailing partial triangle is ignored.
+    /// `shader` receives the interpolated varyings of each covered pixel
+    /// and returns its color.
+    pub fn draw_triangles<const N: usize>(
+        &mut self,
+        vertices: &[ClipVertex<N>],
+        mut shader: impl FnMut(&[f32; N]) -> Color,
+    ) {
+        let (w, h) = (self.width() as f32, self.height() as f32);
+        let mut poly = Vec::with_capacity(9);
+        let mut scratch = Vec::with_capacity(9);
+        let mut screen = Vec::with_capacity(9);
+        for tri in vertices.chunks_exact(3) {
+            poly.clear();
+            poly.extend_from_slice(tri);
+            clip_polygon(&mut poly, &mut scratch);
+
+            screen.clear();
+            screen.extend(poly.iter().map(|v| {
+                let inv_w = 1.0 / v.position[3];
+                ScreenVertex {
+                    x: (v.position[0] * inv_w + 1.0) * 0.5 * w,
+                    y: (1.0 - v.position[1] * inv_w) * 0.5 * h,
+                    z: (v.position[2] * inv_w + 1.0) * 0.5,
+                    inv_w,
+                    varyings: v.varyings.map(|a| a * inv_w),
+                }
+            }));
+            // Clipping keeps the polygon convex, so a fan covers it.
+            for i in 1..screen.len().saturating_sub(1) {
+                self.fill_triangle([&screen[0], &screen[i], &screen[i + 1]], &mut shader);
+            }
+        }
+    }
+
+    fn fill_triangle<const N: usize>(
+        &mut self,
+        [a, b, c]: [&ScreenVertex<N>; 3],
+        shader: &mut impl FnMut(&[f32; N]) -> Color,
+    ) {
+        let area = edge(a.x, a.y, b.x, b.y, c.x, c.y);
+        if area == 0.0 || !area.is_finite() {
+            return;
+        }
+        // y points down in window space, so counter-clockwise in NDC is
+        // a negative area here.
+        let front = area < 0.0;
+        match self.cull {
+            CullMode::Back if !front => return,
+            CullMode::Front if front => return,
+            _ => {}
+        }
+        let (b, c, area) = if front { (c, b, -area) } else { (b, c, area) };
+
+        let (width, height) = (self.width(), self.height());
+        let min_x = (a.x.min(b.x).min(c.x).floor() as i32).max(0);
+        let max_x = (a.x.max(b.x).max(c.x).ceil() as i32).min(width);
+        let min_y = (a.y.min(b.y).min(c.y).floor() as i32).max(0);
+        let max_y = (a.y.max(b.y).max(c.y).ceil() as i32).min(height);
+        let covers = |e: f32, from: &ScreenVertex<N>, to: &ScreenVertex<N>| {
+            e > 0.0 || (e == 0.0 && is_top_left(from, to))
+        };
+
+        for y in min_y..max_y {
+            let py = y as f32 + 0.5;
+            for x in min_x..max_x {
+                let px = x as f32 + 0.5;
+                let e0 = edge(b.x, b.y, c.x, c.y, px, py);
+                let e1 = edge(c.x, c.y, a.x, a.y, px, py);
+                let e2 = edge(a.x, a.y, b.x, b.y, px, py);
+                if !(covers(e0, b, c) && covers(e1, c, a) && covers(e2, a, b)) {
+                    continue;
+                }
+                let (l0, l1, l2) = (e0 / area, e1 / area, e2 / area);
+                let idx = (y * width + x) as usize;
+                let z = l0 * a.z + l1 * b.z + l2 * c.z;
+                if self.depth_test && z >= self.depth[idx] {
+                    continue;
+                }
+                let q = l0 * a.inv_w + l1 * b.inv_w + l2 * c.inv_w;
+                let varyings: [f32; N] = std::array::from_fn(|i| {
+                    (l0 * a.varyings[i] + l1 * b.varyings[i] + l2 * c.varyings[i]) / q
+                });
+                let color = shader(&varyings);
+                if self.depth_test {
+                    self.depth[idx] = z;
+                }
+                let src = [color.r, color.g, color.b, color.a];
+                let out = match self.blend {
+                    Some(mode) => {
+                        let dst = self.target.get_pixel_f32(x, y).unwrap_or([0.0; 4]);
+                        blend_rgba(mode, dst, src)
+                    }
+                    None => src,
+                };
+                self.target.set_pixel_f32(x, y, out);
+            }
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Resizing, cropping, flipping, rotation and blitting.
+
+use crate::Vec2;
+use crate::rect::Rect;
+use crate::texture::Texture;
+use crate::texture::blend::BlendMode;
+use crate::texture::float_image::FloatImage;
+use std::f32::consts::PI;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResizeFilter {
+    Nearest,
+    Bilinear,
+    /// Catmull-Rom cubic.
+    Bicubic,
+    /// Lanczos with a 3-lobe window.
+    Lanczos3,
+}
+
+impl ResizeFilter {
+    pub(crate) fn radius(self) -> f32 {
+        match self {
+            ResizeFilter::Nearest => 0.5,
+            ResizeFilter::Bilinear => 1.0,
+            ResizeFilter::Bicubic => 2.0,
+            ResizeFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    pub(crate) fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            ResizeFilter::Nearest => {
+                if x < 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            ResizeFilter::Bilinear => (1.0 - x).max(0.0),
+            ResizeFilter::Bicubic => {
+                if x < 1.0 {
+                    1.5 * x * x * x - 2.5 * x * x + 1.0
+                } else if x < 2.0 {
+                    -0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0
+                } else {
+                    0.0
+                }
+            }
+            ResizeFilter::Lanczos3 => {
+                if x == 0.0 {
+                    1.0
+                } else if x < 3.0 {
+                    let px = PI * x;
+                    3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// Precomputed filter taps for one output coordinate.
+pub(crate) struct Taps {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+fn compute_taps(src_len: usize, dst_len: usize, filter: ResizeFilter) -> Vec<Taps> {
+    if filter == ResizeFilter::Nearest {
+        let scale = src_len as f32 / dst_len as f32;
+        return (0..dst_len)
+            .map(|i| Taps {
+                start: (((i as f32 + 0.5) * scale) as usize).min(src_len - 1),
+                weights: vec![1.0],
+            })
+            .collect();
+    }
+    compute_taps_with(src_len, dst_len, filter.radius(), |x| filter.weight(x))
+}
+
+/// Taps for a filter of `radius` output pixels whose weight is `weight(x)`.
+pub(crate) fn compute_taps_with(
+    src_len: usize,
+    dst_len: usize,
+    radius: f32,
+    weight: impl Fn(f32) -> f32,
+) -> Vec<Taps> {
+    let scale = src_len as f32 / dst_len as f32;
+    // Widen the filter when shrinking so every source pixel contributes.
+    let support_scale = scale.max(1.0);
+    let radius = radius * support_scale;
+    (0..dst_len)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * scale;
+            let lo = ((center - radius).floor() as isize).max(0) as usize;
+            let hi = ((center + radius).ceil() as usize).min(src_len);
+            let mut weights: Vec<f32> = (lo..hi)
+                .map(|s| weight((s as f32 + 0.5 - center) / support_scale))
+                .collect();
+            let sum: f32 = weights.iter().sum();
+            if sum != 0.0 {
+                weights.iter_mut().for_each(|w| *w /= sum);
+            }
+            Taps { start: lo, weights }
+        })
+        .collect()
+}
+
+impl FloatImage {
+    /// Separable resample to `x_taps.len()` × `y_taps.len()`.
+    pub(crate) fn resample(&self, x_taps: &[Taps], y_taps: &[Taps]) -> FloatImage {
+        let (sw, sh, ch) = (self.width, self.height, self.channels);
+        let (dw, dh) = (x_taps.len(), y_taps.len());
+
+        // Horizontal pass: sw × sh -> dw × sh.
+        let mut tmp = vec![0f32; dw * sh * ch];
+        for y in 0..sh {
+            for (x, taps) in x_taps.iter().enumerate() {
+                for (k, w) in taps.weights.iter().enumerate() {
+                    let s = (y * sw + taps.start + k) * ch;
+                    let d = (y * dw + x) * ch;
+                    for c in 0..ch {
+                        tmp[d + c] += self.data[s + c] * w;
+                    }
+                }
+            }
+        }
+
+        // Vertical pass: dw × sh -> dw × dh.
+        let mut data = vec![0f32; dw * dh * ch];
+        for (y, taps) in y_taps.iter().enumerate() {
+            for x in 0..dw {
+                let d = (y * dw + x) * ch;
+                for (k, w) in taps.weights.iter().enumerate() {
+                    let s = ((taps.start + k) * dw + x) * ch;
+                    for c in 0..ch {
+                        data[d + c] += tmp[s + c] * w;
+                    }
+                }
+            }
+        }
+        FloatImage {
+            width: dw,
+            height: dh,
+            channels: ch,
+            data,
+        }
+    }
+}
+
+impl Texture {
+    /// Resample to `width` × `height` with a separable filter.
+    /// Textures with alpha are filtered premultiplied to avoid dark fringes.
+    pub fn resize(&self, width: i32, height: i32, filter: ResizeFilter) -> Texture {
+        if width <= 0 || height <= 0 || self.width <= 0 || self.height <= 0 {
+            return Texture::new(width.max(0), height.max(0), self.format);
+        }
+        let src = FloatImage::from_texture(self, true);
+        let x_taps = compute_taps(src.width, width as usize, filter);
+        let y_taps = compute_taps(src.height, height as usize, filter);
+        src.resample(&x_taps, &y_taps)
+            .into_texture(self.format, true)
+    }
+
+    /// Copy of the region [x, x + w) × [y, y + h), clipped to the texture.
+    pub fn crop(&self, x: i32, y: i32, w: i32, h: i32) -> Texture {
+        let x0 = x.clamp(0, self.width.max(0));
+        let y0 = y.clamp(0, self.height.max(0));
+        let x1 = x.saturating_add(w.max(0)).clamp(x0, self.width.max(0));
+        let y1 = y.saturating_add(h.max(0)).clamp(y0, self.height.max(0));
+        let mut out = Texture::new(x1 - x0, y1 - y0, self.format);
+        let row = (x1 - x0) as usize * self.format.bytes_per_pixel();
+        for (dy, sy) in (y0..y1).enumerate() {
+            let s = self.index(x0, sy);
+            let d = dy * row;
+            if let Some(src) = self.data.get(s..s + row) {
+                out.data[d..d + row].copy_from_slice(src);
+            }
+        }
+        out
+    }
+
+    /// Mirror left to right in place.
+    pub fn flip_h(&mut self) {
+        let bpp = self.format.bytes_per_pixel();
+        let row = self.width as usize * bpp;
+        if row == 0 {
+            return;
+        }
+        for line in self.data.chunks_exact_mut(row) {
+            let w = line.len() / bpp;
+            for x in 0..w / 2 {
+                for c in 0..bpp {
+                    line.swap(x * bpp + c, (w - 1 - x) * bpp + c);
+                }
+            }
+        }
+    }
+
+    /// Mirror top to bottom in place.
+    pub fn flip_v(&mut self) {
+        let row = self.width as usize * self.format.bytes_per_pixel();
+        let h = self.height as usize;
+        if row == 0 || self.data.len() < row * h {
+            return;
+        }
+        for y in 0..h / 2 {
+            let (top, bottom) = self.data.split_at_mut((h - 1 - y) * row);
+            top[y * row..(y + 1) * row].swap_with_slice(&mut bottom[..row]);
+        }
+    }
+
+    /// Rotate a quarter turn clockwise.
+    pub fn rotate90(&self) -> Texture {
+        self.rotate_quarter(true)
+    }
+
+    /// Rotate a quarter turn counter-clockwise.
+    pub fn rotate270(&self) -> Texture {
+        self.rotate_quarter(false)
+    }
+
+    /// Rotate a half turn.
+    pub fn rotate180(&self) -> Texture {
+        let mut out = self.clone();
+        out.flip_h();
+        out.flip_v();
+        out
+    }
+
+    fn rotate_quarter(&self, clockwise: bool) -> Texture {
+        let bpp = self.format.bytes_per_pixel();
+        let mut out = Texture::new(self.height, self.width, self.format);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (dx, dy) = if clockwise {
+                    (self.height - 1 - y, x)
+                } else {
+                    (y, self.width - 1 - x)
+                };
+                let s = self.index(x, y);
+                let d = out.index(dx, dy);
+                if let Some(src) = self.data.get(s..s + bpp) {
+                    out.data[d..d + bpp].copy_from_slice(src);
+                }
+            }
+        }
+        out
+    }
+
+    /// Draw `src_rect` of `src` with its top-left corner at `dst_pos`,
+    /// blending each pixel with `mode`. Rectangles are rounded to whole
+    /// pixels and clipped against both textures, which must use 8-bit formats.
+    pub fn blit(&mut self, src: &Texture, src_rect: Rect, dst_pos: Vec2, mode: BlendMode) {
+        // In i64, so extreme positions clip instead of overflowing.
+        let sx0 = src_rect.x.round() as i64;
+        let sy0 = src_rect.y.round() as i64;
+        let sx1 = (src_rect.x + src_rect.w).round() as i64;
+        let sy1 = (src_rect.y + src_rect.h).round() as i64;
+        let dx = (dst_pos.x.round() as i64).saturating_sub(sx0);
+        let dy = (dst_pos.y.round() as i64).saturating_sub(sy0);
+
+        let x0 = sx0.max(0).max(dx.saturating_neg());
+        let y0 = sy0.max(0).max(dy.saturating_neg());
+        let x1 = sx1
+            .min(src.width as i64)
+            .min((self.width as i64).saturating_sub(dx));
+        let y1 = sy1
+            .min(src.height as i64)
+            .min((self.height as i64).saturating_sub(dy));
+        for y in y0..y1 {
+            for x in x0..x1 {
+                // Both stay within the textures, so they fit in i32.
+                if let Some(px) = src.get_pixel(x as i32, y as i32) {
+                    self.blend_pixel((x + dx) as i32, (y + dy) as i32, px, mode);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Signed distance fields from coverage masks.
+//!
+//! A pixel is inside the shape when its coverage is at least 0.5. Coverage
+//! is read from alpha for formats that have it and from the first channel
+//! otherwise, so font atlas bitmaps (`R8`) work directly.
+
+use crate::texture::{PixelFormat, Texture};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SdfMethod {
+    /// Exact Euclidean distance (Felzenszwalb & Huttenlocher), O(n).
+    #[default]
+    Exact,
+    /// 8-point sequential signed Euclidean distance transform. Faster in
+    /// practice with small errors along some diagonal edges.
+    Ssedt8,
+}
+
+const FAR: f64 = 1e20;
+
+/// 1D squared distance transform of `f` in place; `v` and `z` are scratch.
+fn edt_1d(f: &mut [f64], v: &mut [usize], z: &mut [f64], d: &mut [f64]) {
+    let n = f.len();
+    if n == 0 {
+        return;
+    }
+    let mut k = 0;
+    v[0] = 0;
+    z[0] = f64::NEG_INFINITY;
+    z[1] = f64::INFINITY;
+    let intersect = |q: usize, p: usize| {
+        ((f[q] + (q * q) as f64) - (f[p] + (p * p) as f64)) / (2 * q - 2 * p) as f64
+    };
+    for q in 1..n {
+        let mut s = intersect(q, v[k]);
+        while s <= z[k] {
+            k -= 1;
+            s = intersect(q, v[k]);
+        }
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = f64::INFINITY;
+    }
+    k = 0;
+    for (q, out) in d.iter_mut().enumerate().take(n) {
+        while z[k + 1] < q as f64 {
+            k += 1;
+        }
+        let dq = q as f64 - v[k] as f64;
+        *out = dq * dq + f[v[k]];
+    }
+    f.copy_from_slice(&d[..n]);
+}
+
+/// Squared distance from every pixel to the nearest `true` pixel of `seeds`.
+fn edt_exact(seeds: &[bool], w: usize, h: usize) -> Vec<f32> {
+    if w == 0 || h == 0 {
+        return vec![];
+    }
+    let mut grid: Vec<f64> = seeds.iter().map(|&s| if s { 0.0 } else { FAR }).collect();
+    let n = w.max(h);
+    let (mut v, mut z, mut d) = (vec![0; n], vec![0.0; n + 1], vec![0.0; n]);
+    let mut col = vec![0.0; h];
+    for x in 0..w {
+        for y in 0..h {
+            col[y] = grid[y * w + x];
+        }
+        edt_1d(&mut col, &mut v, &mut z, &mut d);
+        for y in 0..h {
+            grid[y * w + x] = col[y];
+        }
+    }
+    for row in grid.chunks_exact_mut(w) {
+        edt_1d(row, &mut v, &mut z, &mut d);
+    }
+    grid.into_iter().map(|d| d as f32).collect()
+}
+
+/// Squared distance to the nearest `true` pixel using the 8SSEDT sweeps.
+fn edt_ssedt8(seeds: &[bool], w: usize, h: usize) -> Vec<f32> {
+    const EMPTY: (i32, i32) = (1 << 20, 1 << 20);
+    let mut grid: Vec<(i32, i32)> = seeds
+        .iter()
+        .map(|&s| if s { (0, 0) } else { EMPTY })
+        .collect();
+    let len2 = |p: (i32, i32)| p.0 as i64 * p.0 as i64 + p.1 as i64 * p.1 as i64;
+    let compare = |grid: &mut [(i32, i32)], x: usize, y: usize, ox: i32, oy: i32| {
+        let (nx, ny) = (x as i32 + ox, y as i32 + oy);
+        if nx < 0 || ny < 0 || nx >= w as i32 || ny >= h as i32 {
+            return;
+        }
+        let other = grid[ny as usize * w + nx as usize];
+        if other == EMPTY {
+            return;
+        }
+        let candidate = (other.0 + ox, other.1 + oy);
+        let here = &mut grid[y * w + x];
+        if len2(candidate) < len2(*here) {
+            *here = candidate;
+        }
+    };
+
+    for y in 0..h {
+        for x in 0..w {
+            compare(&mut grid, x, y, -1, 0);
+            compare(&mut grid, x, y, 0, -1);
+            compare(&mut grid, x, y, -1, -1);
+            compare(&mut grid, x, y, 1, -1);
+        }
+        for x in (0..w).rev() {
+            compare(&mut grid, x, y, 1, 0);
+        }
+    }
+    for y in (0..h).rev() {
+        for x in (0..w).rev() {
+            compare(&mut grid, x, y, 1, 0);
+            compare(&mut grid, x, y, 0, 1);
+            compare(&mut grid, x, y, -1, 1);
+            compare(&mut grid, x, y, 1, 1);
+        }
+        for x in 0..w {
+            compare(&mut grid, x, y, -1, 0);
+        }
+    }
+    grid.into_iter()
+        .map(|p| {
+            if p == EMPTY {
+                FAR as f32
+            } else {
+                len2(p) as f32
+            }
+        })
+        .collect()
+}
+
+impl Texture {
+    /// Signed distance in pixels from each pixel center to the shape edge,
+    /// negative inside. Returned row-major, `width * height` long.
+    pub fn signed_distance(&self, method: SdfMethod) -> Vec<f32> {
+        let (w, h) = (self.width.max(0) as usize, self.height.max(0) as usize);
+        let bpp = self.format.bytes_per_pixel();
+        let alpha = if self.format.has_alpha() {
+            self.format.channels() - 1
+        } else {
+            0
+        };
+        let inside: Vec<bool> = (0..w * h)
+            .map(|i| {
+                self.data
+                    .get(i * bpp..(i + 1) * bpp)
+                    .is_some_and(|px| self.format.read_channel(px, alpha) >= 0.5)
+            })
+            .collect();
+        let outside: Vec<bool> = inside.iter().map(|&i| !i).collect();
+        let transform = match method {
+            SdfMethod::Exact => edt_exact,
+            SdfMethod::Ssedt8 => edt_ssedt8,
+        };
+        let to_inside = transform(&inside, w, h);
+        let to_outside = transform(&outside, w, h);
+        // The edge lies halfway between an inside and an outside pixel center.
+        inside
+            .iter()
+            .zip(to_inside.iter().zip(&to_outside))
+            .map(|(&is_in, (&di, &d_o))| {
+                if is_in {
+                    -(d_o.sqrt() - 0.5)
+                } else {
+                    di.sqrt() - 0.5
+                }
+            })
+            .collect()
+    }
+
+    /// `R8` signed distance field: 128 on the edge, rising to 255 `spread`
+    /// pixels inside and falling to 0 `spread` pixels outside. Render with
+    /// `smoothstep` around 0.5 to get crisp edges at any scale.
+    pub fn signed_distance_field(&self, spread: f32, method: SdfMethod) -> Texture {
+        let mut out = Texture::new(self.width.max(0), self.height.max(0), PixelFormat::R8);
+        let spread = spread.max(f32::EPSILON);
+        for (d, dist) in out.data.iter_mut().zip(self.signed_distance(method)) {
+            *d = ((0.5 - dist / (2.0 * spread)).clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+        out
+    }
+}
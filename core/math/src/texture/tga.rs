@@ -0,0 +1,275 @@
+//! TGA decoding and encoding.
+//!
+//! Decodes color-mapped, truecolor and grayscale images, both raw and RLE,
+//! with 8, 15/16, 24 and 32 bits per pixel. Encodes uncompressed images
+//! with a top-left origin.
+
+use crate::texture::{ImageError, PixelFormat, Texture, decoded_size};
+
+fn corrupt(msg: &str) -> ImageError {
+    ImageError::Corrupt(format!("tga: {}", msg))
+}
+
+/// Convert one stored pixel (BGR(A) or 16-bit ARGB1555) to RGBA.
+fn to_rgba(px: &[u8]) -> [u8; 4] {
+    match px.len() {
+        1 => [px[0], px[0], px[0], 255],
+        2 => {
+            let v = u16::from_le_bytes([px[0], px[1]]);
+            let expand = |c: u16| ((c & 0x1f) * 255 / 31) as u8;
+            [expand(v >> 10), expand(v >> 5), expand(v), 255]
+        }
+        3 => [px[2], px[1], px[0], 255],
+        _ => [px[2], px[1], px[0], px[3]],
+    }
+}
+
+/// Decode a TGA file held in memory.
+pub fn decode(bytes: &[u8]) -> Result<Texture, ImageError> {
+    if bytes.len() < 18 {
+        return Err(corrupt("header too short"));
+    }
+    let id_len = bytes[0] as usize;
+    let has_map = bytes[1] == 1;
+    let image_type = bytes[2];
+    let map_first = u16::from_le_bytes([bytes[3], bytes[4]]) as usize;
+    let map_len = u16::from_le_bytes([bytes[5], bytes[6]]) as usize;
+    let map_bits = bytes[7] as usize;
+    let width = u16::from_le_bytes([bytes[12], bytes[13]]) as usize;
+    let height = u16::from_le_bytes([bytes[14], bytes[15]]) as usize;
+    let bits = bytes[16] as usize;
+    let descriptor = bytes[17];
+
+    let rle = image_type & 8 != 0;
+    let (mapped, gray) = match image_type & 7 {
+        1 => (true, false),
+        2 => (false, false),
+        3 => (false, true),
+        _ => {
+            return Err(ImageError::Unsupported(format!(
+                "tga: image type {}",
+                image_type
+            )));
+        }
+    };
+    let pixel_bytes = match bits {
+        8 => 1,
+        15 | 16 => 2,
+        24 => 3,
+        32 => 4,
+        _ => {
+            return Err(ImageError::Unsupported(format!(
+                "tga: {} bits per pixel",
+                bits
+            )));
+        }
+    };
+    if mapped && (!has_map || pixel_bytes > 2) {
+        return Err(corrupt("color-mapped image without a usable color map"));
+    }
+
+    let has_alpha = if mapped {
+        map_bits == 32
+    } else {
+        pixel_bytes == 4
+    };
+    let format = if gray {
+        PixelFormat::R8
+    } else if has_alpha {
+        PixelFormat::RGBA8
+    } else {
+        PixelFormat::RGB8
+    };
+    let channels = format.channels();
+    let count = decoded_size(width, height, channels)
+        .ok_or_else(|| corrupt("invalid image dimensions"))?
+        / channels;
+
+    let mut pos = 18 + id_len;
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+    if has_map {
+        let entry_bytes = map_bits.div_ceil(8);
+        if !(1..=4).contains(&entry_bytes) {
+            return Err(corrupt("invalid color map entry size"));
+        }
+        let map = bytes
+            .get(pos..pos + map_len * entry_bytes)
+            .ok_or_else(|| corrupt("truncated color map"))?;
+        palette = map.chunks(entry_bytes).map(to_rgba).collect();
+        pos += map_len * entry_bytes;
+    }
+
+    // Gather the raw stored pixels, expanding RLE packets. The header is
+    // untrusted, so reserve no more than the bytes left in the file.
+    let remaining = bytes.len().saturating_sub(pos);
+    let mut stored = Vec::with_capacity((count * pixel_bytes).min(remaining));
+    if rle {
+        while stored.len() < count * pixel_bytes {
+            let packet = *bytes
+                .get(pos)
+                .ok_or_else(|| corrupt("truncated RLE data"))?;
+            pos += 1;
+            let run = (packet & 0x7f) as usize + 1;
+            if packet & 0x80 != 0 {
+                let px = bytes
+                    .get(pos..pos + pixel_bytes)
+                    .ok_or_else(|| corrupt("truncated RLE data"))?;
+                for _ in 0..run {
+                    stored.extend_from_slice(px);
+                }
+                pos += pixel_bytes;
+            } else {
+                let px = bytes
+                    .get(pos..pos + run * pixel_bytes)
+                    .ok_or_else(|| corrupt("truncated RLE data"))?;
+                stored.extend_from_slice(px);
+                pos += run * pixel_bytes;
+            }
+        }
+        stored.truncate(count * pixel_bytes);
+    } else {
+        let px = bytes
+            .get(pos..pos + count * pixel_bytes)
+            .ok_or_else(|| corrupt("truncated pixel data"))?;
+        stored.extend_from_slice(px);
+    }
+
+    let mut texture = Texture::new(width as i32, height as i32, format);
+
+    let top_down = descriptor & 0x20 != 0;
+    let right_to_left = descriptor & 0x10 != 0;
+    for (i, px) in stored.chunks(pixel_bytes).enumerate() {
+        let (sx, sy) = (i % width, i / width);
+        let x = if right_to_left { width - 1 - sx } else { sx };
+        let y = if top_down { sy } else { height - 1 - sy };
+        let dst = (y * width + x) * channels;
+        if gray {
+            texture.data[dst] = px[0];
+            continue;
+        }
+        let rgba = if mapped {
+            let index = if pixel_bytes == 1 {
+                px[0] as usize
+            } else {
+                u16::from_le_bytes([px[0], px[1]]) as usize
+            };
+            *palette
+                .get(index.wrapping_sub(map_first))
+                .ok_or_else(|| corrupt("color map index out of range"))?
+        } else {
+            to_rgba(px)
+        };
+        texture.data[dst..dst + channels].copy_from_slice(&rgba[..channels]);
+    }
+    Ok(texture)
+}
+
+/// Encode as an uncompressed grayscale, 24-bit or 32-bit TGA.
+pub fn encode(texture: &Texture) -> Result<Vec<u8>, ImageError> {
+    let (image_type, bits) = match texture.format {
+        PixelFormat::R8 => (3u8, 8u8),
+        PixelFormat::RGB8 => (2, 24),
+        PixelFormat::RGBA8 => (2, 32),
+        f => {
+            return Err(ImageError::Unsupported(format!(
+                "tga: cannot encode {:?}, convert to R8, RGB8 or RGBA8 first",
+                f
+            )));
+        }
+    };
+    if texture.width > u16::MAX as i32 || texture.height > u16::MAX as i32 {
+        return Err(ImageError::Unsupported(
+            "tga: dimensions larger than 65535".to_string(),
+        ));
+    }
+    let channels = texture.channels();
+    let count = texture.width.max(0) as usize * texture.height.max(0) as usize;
+    if texture.data.len() < count * channels {
+        return Err(ImageError::InvalidTexture(
+            "data is smaller than width * height * channels".to_string(),
+        ));
+    }
+
+    let alpha_bits = if channels == 4 { 8 } else { 0 };
+    let mut out = vec![0, 0, image_type, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    out.extend_from_slice(&(texture.width as u16).to_le_bytes());
+    out.extend_from_slice(&(texture.height as u16).to_le_bytes());
+    out.push(bits);
+    out.push(0x20 | alpha_bits); // top-left origin
+    for px in texture.data[..count * channels].chunks(channels) {
+        match channels {
+            1 => out.push(px[0]),
+            3 => out.extend_from_slice(&[px[2], px[1], px[0]]),
+            _ => out.extend_from_slice(&[px[2], px[1], px[0], px[3]]),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(format: PixelFormat) -> Texture {
+        let mut texture = Texture::new(5, 3, format);
+        for (i, d) in texture.data.iter_mut().enumerate() {
+            *d = (i * 37) as u8;
+        }
+        texture
+    }
+
+    fn header(image_type: u8, width: u16, height: u16, bits: u8) -> Vec<u8> {
+        let mut bytes = vec![0; 18];
+        bytes[2] = image_type;
+        bytes[12..14].copy_from_slice(&width.to_le_bytes());
+        bytes[14..16].copy_from_slice(&height.to_le_bytes());
+        bytes[16] = bits;
+        // Top-left origin.
+        bytes[17] = 0x20;
+        bytes
+    }
+
+    #[test]
+    fn round_trips_8bit_formats() {
+        for format in [PixelFormat::R8, PixelFormat::RGB8, PixelFormat::RGBA8] {
+            let texture = gradient(format);
+            let decoded = decode(&encode(&texture).unwrap()).unwrap();
+            assert_eq!((decoded.width, decoded.height), (5, 3));
+            assert_eq!(decoded.format, format);
+            assert_eq!(decoded.data, texture.data);
+        }
+    }
+
+    #[test]
+    fn decodes_rle_packets() {
+        let mut bytes = header(10, 3, 1, 24);
+        // A run of two blue pixels, then one raw red pixel (stored BGR).
+        bytes.extend_from_slice(&[0x81, 255, 0, 0, 0x00, 0, 0, 255]);
+        let texture = decode(&bytes).unwrap();
+        assert_eq!(texture.data, [0, 0, 255, 0, 0, 255, 255, 0, 0]);
+    }
+
+    #[test]
+    fn rejects_truncated_pixel_data() {
+        let bytes = encode(&gradient(PixelFormat::RGB8)).unwrap();
+        assert!(matches!(
+            decode(&bytes[..bytes.len() - 1]),
+            Err(ImageError::Corrupt(_))
+        ));
+        let mut rle = header(10, 4, 4, 24);
+        rle.extend_from_slice(&[0x81, 1, 2, 3]);
+        assert!(matches!(decode(&rle), Err(ImageError::Corrupt(_))));
+    }
+
+    #[test]
+    fn rejects_oversized_dimensions() {
+        let bytes = header(2, u16::MAX, u16::MAX, 32);
+        assert!(matches!(decode(&bytes), Err(ImageError::Corrupt(_))));
+    }
+
+    #[test]
+    fn rejects_unsupported_bit_depth() {
+        let bytes = header(2, 1, 1, 12);
+        assert!(matches!(decode(&bytes), Err(ImageError::Unsupported(_))));
+    }
+}
@@ -0,0 +1,510 @@
+//! Minimal zlib (RFC 1950) / DEFLATE (RFC 1951) codec used by the PNG loader.
+//!
+//! Inflate supports stored, fixed and dynamic Huffman blocks.
+//! Deflate emits fixed Huffman blocks with hash-chain LZ77 matching.
+
+use crate::texture::ImageError;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+fn corrupt(msg: &str) -> ImageError {
+    ImageError::Corrupt(format!("zlib: {}", msg))
+}
+
+fn too_long(limit: usize) -> ImageError {
+    corrupt(&format!("output exceeds {} bytes", limit))
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+    // 5552 is the largest block that cannot overflow before the modulo.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader {
+            data,
+            pos: 0,
+            bit_buf: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn bits(&mut self, n: u32) -> Result<u32, ImageError> {
+        while self.bit_count < n {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| corrupt("unexpected end of stream"))?;
+            self.pos += 1;
+            self.bit_buf |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let v = self.bit_buf & ((1u32 << n) - 1);
+        self.bit_buf >>= n;
+        self.bit_count -= n;
+        Ok(v)
+    }
+
+    fn align_to_byte(&mut self) {
+        self.bit_buf = 0;
+        self.bit_count = 0;
+    }
+}
+
+/// Canonical Huffman decoding table.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, ImageError> {
+        let mut counts = [0u16; 16];
+        for &l in lengths {
+            counts[l as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = left * 2 - count as i32;
+            if left < 0 {
+                return Err(corrupt("over-subscribed Huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; 16];
+        for i in 1..15 {
+            offsets[i + 1] = offsets[i] + counts[i];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (sym, &l) in lengths.iter().enumerate() {
+            if l != 0 {
+                symbols[offsets[l as usize] as usize] = sym as u16;
+                offsets[l as usize] += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, br: &mut BitReader) -> Result<u16, ImageError> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for len in 1..16 {
+            code |= br.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + (code - first)) as usize]);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+        Err(corrupt("invalid Huffman code"))
+    }
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    let lit = Huffman::new(&lengths).unwrap();
+    let dist = Huffman::new(&[5u8; 30]).unwrap();
+    (lit, dist)
+}
+
+fn dynamic_tables(br: &mut BitReader) -> Result<(Huffman, Huffman), ImageError> {
+    let hlit = br.bits(5)? as usize + 257;
+    let hdist = br.bits(5)? as usize + 1;
+    let hclen = br.bits(4)? as usize + 4;
+
+    let mut cl_lengths = [0u8; 19];
+    for &i in CODE_LENGTH_ORDER.iter().take(hclen) {
+        cl_lengths[i] = br.bits(3)? as u8;
+    }
+    let cl = Huffman::new(&cl_lengths)?;
+
+    let mut lengths = vec![0u8; hlit + hdist];
+    let mut i = 0;
+    while i < hlit + hdist {
+        let sym = cl.decode(br)?;
+        let (value, repeat) = match sym {
+            0..=15 => (sym as u8, 1),
+            16 => {
+                if i == 0 {
+                    return Err(corrupt("repeat with no previous length"));
+                }
+                (lengths[i - 1], 3 + br.bits(2)? as usize)
+            }
+            17 => (0, 3 + br.bits(3)? as usize),
+            18 => (0, 11 + br.bits(7)? as usize),
+            _ => return Err(corrupt("invalid code length symbol")),
+        };
+        if i + repeat > hlit + hdist {
+            return Err(corrupt("code lengths overflow"));
+        }
+        lengths[i..i + repeat].fill(value);
+        i += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(corrupt("missing end-of-block code"));
+    }
+    Ok((
+        Huffman::new(&lengths[..hlit])?,
+        Huffman::new(&lengths[hlit..])?,
+    ))
+}
+
+fn inflate_block(
+    br: &mut BitReader,
+    out: &mut Vec<u8>,
+    lit: &Huffman,
+    dist: &Huffman,
+    limit: usize,
+) -> Result<(), ImageError> {
+    loop {
+        if out.len() > limit {
+            return Err(too_long(limit));
+        }
+        let sym = lit.decode(br)? as usize;
+        if sym < 256 {
+            out.push(sym as u8);
+        } else if sym == 256 {
+            return Ok(());
+        } else {
+            let li = sym - 257;
+            if li >= 29 {
+                return Err(corrupt("invalid length symbol"));
+            }
+            let len = LENGTH_BASE[li] as usize + br.bits(LENGTH_EXTRA[li] as u32)? as usize;
+            let di = dist.decode(br)? as usize;
+            if di >= 30 {
+                return Err(corrupt("invalid distance symbol"));
+            }
+            let d = DIST_BASE[di] as usize + br.bits(DIST_EXTRA[di] as u32)? as usize;
+            if d > out.len() {
+                return Err(corrupt("distance too far back"));
+            }
+            let start = out.len() - d;
+            for k in 0..len {
+                out.push(out[start + k]);
+            }
+        }
+    }
+}
+
+/// Decompress a raw DEFLATE stream, failing once the output grows past
+/// `limit` bytes.
+pub fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, ImageError> {
+    let mut br = BitReader::new(data);
+    let mut out = Vec::new();
+    loop {
+        let last = br.bits(1)?;
+        match br.bits(2)? {
+            0 => {
+                br.align_to_byte();
+                let header = data
+                    .get(br.pos..br.pos + 4)
+                    .ok_or_else(|| corrupt("truncated stored block"))?;
+                let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+                let nlen = u16::from_le_bytes([header[2], header[3]]) as usize;
+                if len != !nlen & 0xffff {
+                    return Err(corrupt("stored block length mismatch"));
+                }
+                br.pos += 4;
+                let block = data
+                    .get(br.pos..br.pos + len)
+                    .ok_or_else(|| corrupt("truncated stored block"))?;
+                if out.len() + len > limit {
+                    return Err(too_long(limit));
+                }
+                out.extend_from_slice(block);
+                br.pos += len;
+            }
+            1 => {
+                let (lit, dist) = fixed_tables();
+                inflate_block(&mut br, &mut out, &lit, &dist, limit)?;
+            }
+            2 => {
+                let (lit, dist) = dynamic_tables(&mut br)?;
+                inflate_block(&mut br, &mut out, &lit, &dist, limit)?;
+            }
+            _ => return Err(corrupt("invalid block type")),
+        }
+        if out.len() > limit {
+            return Err(too_long(limit));
+        }
+        if last == 1 {
+            return Ok(out);
+        }
+    }
+}
+
+/// Decompress a zlib-wrapped stream of at most `limit` bytes and verify its
+/// checksum.
+pub fn zlib_decompress(data: &[u8], limit: usize) -> Result<Vec<u8>, ImageError> {
+    if data.len() < 6 {
+        return Err(corrupt("stream too short"));
+    }
+    let (cmf, flg) = (data[0], data[1]);
+    if cmf & 0x0f != 8 {
+        return Err(corrupt("compression method is not deflate"));
+    }
+    if !((cmf as u16) << 8 | flg as u16).is_multiple_of(31) {
+        return Err(corrupt("header checksum mismatch"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(corrupt("preset dictionaries are not supported"));
+    }
+    let out = inflate(&data[2..], limit)?;
+    let tail = &data[data.len() - 4..];
+    let expected = u32::from_be_bytes([tail[0], tail[1], tail[2], tail[3]]);
+    if adler32(&out) != expected {
+        return Err(corrupt("adler32 checksum mismatch"));
+    }
+    Ok(out)
+}
+
+struct BitWriter {
+    out: Vec<u8>,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        BitWriter {
+            out: Vec::new(),
+            bit_buf: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn bits(&mut self, value: u32, n: u32) {
+        self.bit_buf |= value << self.bit_count;
+        self.bit_count += n;
+        while self.bit_count >= 8 {
+            self.out.push(self.bit_buf as u8);
+            self.bit_buf >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// Huffman codes are written most significant bit first.
+    fn code(&mut self, code: u32, len: u32) {
+        let mut rev = 0;
+        for i in 0..len {
+            rev |= ((code >> i) & 1) << (len - 1 - i);
+        }
+        self.bits(rev, len);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.out.push(self.bit_buf as u8);
+        }
+        self.out
+    }
+}
+
+fn write_fixed_literal(bw: &mut BitWriter, sym: u32) {
+    match sym {
+        0..=143 => bw.code(0x30 + sym, 8),
+        144..=255 => bw.code(0x190 + sym - 144, 9),
+        256..=279 => bw.code(sym - 256, 7),
+        _ => bw.code(0xc0 + sym - 280, 8),
+    }
+}
+
+fn write_match(bw: &mut BitWriter, len: usize, dist: usize) {
+    let li = LENGTH_BASE
+        .iter()
+        .rposition(|&b| b as usize <= len)
+        .unwrap();
+    write_fixed_literal(bw, 257 + li as u32);
+    bw.bits(
+        (len - LENGTH_BASE[li] as usize) as u32,
+        LENGTH_EXTRA[li] as u32,
+    );
+    let di = DIST_BASE.iter().rposition(|&b| b as usize <= dist).unwrap();
+    bw.code(di as u32, 5);
+    bw.bits(
+        (dist - DIST_BASE[di] as usize) as u32,
+        DIST_EXTRA[di] as u32,
+    );
+}
+
+const WINDOW: usize = 32768;
+const HASH_SIZE: usize = 1 << 15;
+const MAX_CHAIN: usize = 64;
+
+fn hash3(data: &[u8], i: usize) -> usize {
+    let v = (data[i] as usize) << 16 | (data[i + 1] as usize) << 8 | data[i + 2] as usize;
+    (v.wrapping_mul(2654435761) >> 7) & (HASH_SIZE - 1)
+}
+
+fn insert_hash(data: &[u8], i: usize, head: &mut [usize], prev: &mut [usize]) {
+    if i + 3 <= data.len() {
+        let h = hash3(data, i);
+        prev[i] = head[h];
+        head[h] = i;
+    }
+}
+
+/// Compress to a raw DEFLATE stream (single fixed Huffman block).
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut bw = BitWriter::new();
+    bw.bits(1, 1); // final block
+    bw.bits(1, 2); // fixed Huffman
+
+    let mut head = vec![usize::MAX; HASH_SIZE];
+    let mut prev = vec![usize::MAX; data.len()];
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut best_len = 0;
+        let mut best_dist = 0;
+        if i + 3 <= data.len() {
+            let max_len = (data.len() - i).min(258);
+            let mut candidate = head[hash3(data, i)];
+            let mut chain = 0;
+            while candidate != usize::MAX && i - candidate <= WINDOW && chain < MAX_CHAIN {
+                let len = data[candidate..]
+                    .iter()
+                    .zip(&data[i..i + max_len])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best_len {
+                    best_len = len;
+                    best_dist = i - candidate;
+                    if len == max_len {
+                        break;
+                    }
+                }
+                candidate = prev[candidate];
+                chain += 1;
+            }
+        }
+
+        if best_len >= 3 {
+            write_match(&mut bw, best_len, best_dist);
+            for k in i..i + best_len {
+                insert_hash(data, k, &mut head, &mut prev);
+            }
+            i += best_len;
+        } else {
+            write_fixed_literal(&mut bw, data[i] as u32);
+            insert_hash(data, i, &mut head, &mut prev);
+            i += 1;
+        }
+    }
+    write_fixed_literal(&mut bw, 256);
+    bw.finish()
+}
+
+/// Compress to a zlib-wrapped stream.
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    out.extend(deflate(data));
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_data() -> Vec<u8> {
+        // Text-like data with repeats, plus a stretch of noise.
+        let mut data = b"the quick brown fox jumps over the lazy dog. ".repeat(50);
+        let mut x = 1u32;
+        for _ in 0..1000 {
+            x = x.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            data.push((x >> 16) as u8);
+        }
+        data
+    }
+
+    #[test]
+    fn round_trips() {
+        for data in [vec![], vec![7], sample_data()] {
+            let compressed = zlib_compress(&data);
+            assert_eq!(zlib_decompress(&compressed, data.len()).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn compresses_repeats() {
+        let data = vec![0; 100_000];
+        assert!(zlib_compress(&data).len() < 1000);
+    }
+
+    #[test]
+    fn inflates_stored_blocks() {
+        // One final stored block holding "abc".
+        let data = [0x01, 3, 0, !3, 0xff, b'a', b'b', b'c'];
+        assert_eq!(inflate(&data, 3).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn stops_at_the_limit() {
+        let compressed = zlib_compress(&vec![0; 100_000]);
+        assert!(matches!(
+            zlib_decompress(&compressed, 1000),
+            Err(ImageError::Corrupt(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_checksum_mismatch() {
+        let mut compressed = zlib_compress(&sample_data());
+        let last = compressed.len() - 1;
+        compressed[last] ^= 1;
+        assert!(zlib_decompress(&compressed, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_stream() {
+        let compressed = zlib_compress(&sample_data());
+        assert!(zlib_decompress(&compressed[..compressed.len() / 2], usize::MAX).is_err());
+    }
+}
@@ -2,6 +2,7 @@ use std::ops::{Add, Mul, Neg, Sub};
 
 use crate::Vec2;
 /// 3D vector with `f32` components.
+#[repr(C)]
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Vec3 {
     pub x: f32,
@@ -0,0 +1,1125 @@
+//! The GPU operations the renderers issue, behind a trait so renderer
+//! logic can run against something other than the cengine GL context.
+//!
+//! `GlBackend` forwards to cengine. `RecordingBackend` keeps every call in
+//! memory instead, which lets tests check what a renderer would draw on a
+//! machine without a GPU or display.
+//!
+//! A backend value is a handle to a context: clones act on the same
+//! context, which is what lets the resource handles in `handles` release
+//! themselves on drop.
+
+use crate::c;
+use crate::error::EngineError;
+use crate::helper_functions::*;
+use crate::uniform::Uniform;
+use math::{texture::*, *};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// How a draw call assembles vertices into primitives. Values are the GL
+/// primitive modes.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Primitive {
+    Points = 0x0000,
+    Lines = 0x0001,
+    LineLoop = 0x0002,
+    LineStrip = 0x0003,
+    Triangles = 0x0004,
+    TriangleStrip = 0x0005,
+    TriangleFan = 0x0006,
+}
+
+/// Contents of an element buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indices<'a> {
+    U16(&'a [u16]),
+    U32(&'a [u32]),
+}
+
+/// Type of the indices in an element buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexType {
+    U16,
+    U32,
+}
+
+impl IndexType {
+    pub fn size(self) -> usize {
+        match self {
+            IndexType::U16 => 2,
+            IndexType::U32 => 4,
+        }
+    }
+}
+
+/// An integer type usable as a vertex index.
+pub trait ElementIndex: Copy {
+    const TYPE: IndexType;
+
+    fn indices(indices: &[Self]) -> Indices<'_>;
+}
+
+impl ElementIndex for u16 {
+    const TYPE: IndexType = IndexType::U16;
+
+    fn indices(indices: &[u16]) -> Indices<'_> {
+        Indices::U16(indices)
+    }
+}
+
+impl ElementIndex for u32 {
+    const TYPE: IndexType = IndexType::U32;
+
+    fn indices(indices: &[u32]) -> Indices<'_> {
+        Indices::U32(indices)
+    }
+}
+
+/// A framebuffer attachment point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attachment {
+    Color(u32),
+    Depth,
+}
+
+/// Resource handles are plain ids, as in GL. Binding calls select the
+/// object later calls act on: vertex uploads and attribute pointers apply
+/// to the bound buffer and vertex array, texture uploads and draws to the
+/// bound texture and program.
+pub trait RenderBackend: Clone {
+    fn create_program(
+        &mut self,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Result<u32, EngineError>;
+    fn create_vertex_array(&mut self) -> u32;
+    fn create_buffer(&mut self) -> u32;
+    fn create_texture(&mut self) -> u32;
+    fn create_framebuffer(&mut self) -> u32;
+
+    fn delete_program(&mut self, program: u32);
+    fn delete_vertex_array(&mut self, vao: u32);
+    fn delete_buffer(&mut self, vbo: u32);
+    fn delete_texture(&mut self, texture: u32);
+    fn delete_framebuffer(&mut self, fbo: u32);
+
+    fn bind_program(&mut self, program: u32);
+    fn bind_vertex_array(&mut self, vao: u32);
+    fn bind_buffer(&mut self, vbo: u32);
+    fn bind_texture(&mut self, texture: u32);
+    /// Bind `ebo` as the element buffer of the bound vertex array.
+    fn bind_element_buffer(&mut self, ebo: u32);
+    /// Draw into and read from `fbo`, whose attachments are `size`; 0 is
+    /// the window.
+    fn bind_framebuffer(&mut self, fbo: u32, size: Vec2i);
+    /// Attach `texture` to the bound framebuffer.
+    fn attach_texture(&mut self, attachment: Attachment, texture: u32);
+    /// Draw into the first `count` color attachments of the bound
+    /// framebuffer.
+    fn set_draw_buffers(&mut self, count: u32);
+    fn framebuffer_complete(&mut self) -> bool;
+
+    /// Describe float attribute `index`: `count` floats at byte `offset`
+    /// into each `stride`-byte vertex of the bound buffer.
+    fn vertex_attrib_pointer_float(&mut self, index: u32, count: i32, stride: u32, offset: usize);
+    /// Advance attribute `index` once per `divisor` instances; 0 advances
+    /// it per vertex.
+    fn vertex_attrib_divisor(&mut self, index: u32, divisor: u32);
+    /// Replace the contents of the bound buffer.
+    fn update_vertices_dynamic(&mut self, vertices: &[f32]);
+    /// Replace the contents of the bound buffer with vertices that are
+    /// drawn many times without changing.
+    fn update_vertices_static(&mut self, vertices: &[f32]);
+    /// Replace the contents of the bound element buffer.
+    fn update_indices_dynamic(&mut self, indices: Indices);
+    /// Replace the image of the bound texture.
+    fn tex_image_2d(&mut self, texture: &Texture);
+    /// Build the mip chain of the bound texture from its image and sample
+    /// it with linear blending between levels.
+    fn generate_mipmaps(&mut self);
+
+    /// Location of the active uniform `name` in `program`, or `None` if
+    /// the program has no such uniform.
+    fn uniform_location(&mut self, program: u32, name: &str) -> Option<i32>;
+    /// Set the uniform at `location` of the bound program.
+    fn set_uniform(&mut self, location: i32, value: &Uniform);
+    /// Bind `texture` to texture unit `unit`, for `Uniform::Sampler(unit)`.
+    /// `bind_texture` keeps binding to unit 0.
+    fn bind_texture_unit(&mut self, unit: u32, texture: u32);
+
+    fn set_depth_test(&mut self, enabled: bool);
+    fn set_viewport(&mut self, x: i32, y: i32, width: i32, height: i32);
+    fn window_size(&self) -> Vec2i;
+    /// Size of the bound framebuffer.
+    fn target_size(&self) -> Vec2i;
+    /// Draw `count` vertices of the bound vertex array from `first` on.
+    fn draw_arrays(&mut self, primitive: Primitive, first: u32, count: u32);
+    /// Draw `count` indices of the bound element buffer, starting at the
+    /// index `first`.
+    fn draw_elements(&mut self, primitive: Primitive, first: u32, count: u32, kind: IndexType);
+    /// `draw_arrays` repeated for `instances` instances.
+    fn draw_arrays_instanced(
+        &mut self,
+        primitive: Primitive,
+        first: u32,
+        count: u32,
+        instances: u32,
+    );
+    /// `draw_elements` repeated for `instances` instances.
+    fn draw_elements_instanced(
+        &mut self,
+        primitive: Primitive,
+        first: u32,
+        count: u32,
+        kind: IndexType,
+        instances: u32,
+    );
+
+    /// Draw `vertex_count` vertices of the bound vertex array as triangles.
+    fn draw_triangle_arrays(&mut self, vertex_count: u32) {
+        self.draw_arrays(Primitive::Triangles, 0, vertex_count);
+    }
+}
+
+/// The cengine OpenGL context. `initialize` must have been called first.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GlBackend;
+
+impl RenderBackend for GlBackend {
+    fn create_program(
+        &mut self,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Result<u32, EngineError> {
+        initialize_program(vertex_source, fragment_source)
+    }
+
+    fn create_vertex_array(&mut self) -> u32 {
+        unsafe { c::create_vao() }
+    }
+
+    fn create_buffer(&mut self) -> u32 {
+        unsafe { c::create_vbo() }
+    }
+
+    fn create_texture(&mut self) -> u32 {
+        unsafe { c::create_texture() }
+    }
+
+    fn create_framebuffer(&mut self) -> u32 {
+        unsafe { c::create_framebuffer() }
+    }
+
+    fn delete_program(&mut self, program: u32) {
+        unsafe { c::delete_program(program) }
+    }
+
+    fn delete_vertex_array(&mut self, vao: u32) {
+        unsafe { c::delete_vao(vao) }
+    }
+
+    fn delete_buffer(&mut self, vbo: u32) {
+        unsafe { c::delete_vbo(vbo) }
+    }
+
+    fn delete_texture(&mut self, texture: u32) {
+        unsafe { c::delete_texture(texture) }
+    }
+
+    fn delete_framebuffer(&mut self, fbo: u32) {
+        unsafe { c::delete_framebuffer(fbo) }
+    }
+
+    fn bind_program(&mut self, program: u32) {
+        unsafe { c::bind_program(program) }
+    }
+
+    fn bind_vertex_array(&mut self, vao: u32) {
+        unsafe { c::bind_vao(vao) }
+    }
+
+    fn bind_buffer(&mut self, vbo: u32) {
+        unsafe { c::bind_vbo(vbo) }
+    }
+
+    fn bind_texture(&mut self, texture: u32) {
+        unsafe { c::bind_texture(texture) }
+    }
+
+    fn bind_element_buffer(&mut self, ebo: u32) {
+        unsafe { c::bind_ebo(ebo) }
+    }
+
+    fn bind_framebuffer(&mut self, fbo: u32, size: Vec2i) {
+        unsafe { c::bind_framebuffer(fbo, size.x, size.y) }
+    }
+
+    fn attach_texture(&mut self, attachment: Attachment, texture: u32) {
+        let index = match attachment {
+            Attachment::Color(i) => i as i32,
+            Attachment::Depth => -1,
+        };
+        unsafe { c::framebuffer_texture(index, texture) }
+    }
+
+    fn set_draw_buffers(&mut self, count: u32) {
+        unsafe { c::draw_buffers(count as i32) }
+    }
+
+    fn framebuffer_complete(&mut self) -> bool {
+        unsafe { c::framebuffer_complete() != 0 }
+    }
+
+    fn vertex_attrib_pointer_float(&mut self, index: u32, count: i32, stride: u32, offset: usize) {
+        unsafe { c::vertex_attrib_pointer_float(index, count, stride, offset) }
+    }
+
+    fn vertex_attrib_divisor(&mut self, index: u32, divisor: u32) {
+        unsafe { c::vertex_attrib_divisor(index, divisor) }
+    }
+
+    fn update_vertices_dynamic(&mut self, vertices: &[f32]) {
+        update_vertices_dynamic(vertices);
+    }
+
+    fn update_vertices_static(&mut self, vertices: &[f32]) {
+        update_vertices_static(vertices);
+    }
+
+    fn update_indices_dynamic(&mut self, indices: Indices) {
+        update_indices_dynamic(indices);
+    }
+
+    fn tex_image_2d(&mut self, texture: &Texture) {
+        tex_image_2d(texture);
+    }
+
+    fn generate_mipmaps(&mut self) {
+        unsafe { c::generate_mipmap_2d() }
+    }
+
+    fn uniform_location(&mut self, program: u32, name: &str) -> Option<i32> {
+        let name = get_cstring(name);
+        let location = unsafe { c::get_uniform_location(program, name.as_ptr()) };
+        if location < 0 { None } else { Some(location) }
+    }
+
+    fn set_uniform(&mut self, location: i32, value: &Uniform) {
+        let floats = |components: i32, values: &[f32]| unsafe {
+            let count = values.len() as i32 / components;
+            c::set_uniform_floats(location, components, count, values.as_ptr());
+        };
+        let ints = |values: &[i32]| unsafe {
+            c::set_uniform_ints(location, values.len() as i32, values.as_ptr());
+        };
+        match value {
+            Uniform::Float(v) => floats(1, &[*v]),
+            Uniform::Int(v) => ints(&[*v]),
+            Uniform::Bool(v) => ints(&[*v as i32]),
+            Uniform::Sampler(unit) => ints(&[*unit as i32]),
+            Uniform::Vec2(v) => floats(2, &[v.x, v.y]),
+            Uniform::Vec3(v) => floats(3, &[v.x, v.y, v.z]),
+            Uniform::Vec4(v) => floats(4, v),
+            Uniform::Mat3(m) => unsafe { c::set_uniform_matrix3(location, 1, m.as_ptr()) },
+            Uniform::Mat4(m) => unsafe { c::set_uniform_matrix4(location, 1, m.to_f32_ptr()) },
+            Uniform::FloatArray(v) => floats(1, v),
+            Uniform::IntArray(v) => ints(v),
+            Uniform::Vec2Array(v) => {
+                floats(2, &v.iter().flat_map(|v| [v.x, v.y]).collect::<Vec<_>>())
+            }
+            Uniform::Vec3Array(v) => floats(
+                3,
+                &v.iter().flat_map(|v| [v.x, v.y, v.z]).collect::<Vec<_>>(),
+            ),
+            Uniform::Vec4Array(v) => floats(4, v.as_flattened()),
+            Uniform::Mat4Array(v) => {
+                let values: Vec<f32> = v.iter().flat_map(|m| m.m).collect();
+                unsafe { c::set_uniform_matrix4(location, v.len() as i32, values.as_ptr()) }
+            }
+        }
+    }
+
+    fn bind_texture_unit(&mut self, unit: u32, texture: u32) {
+        unsafe { c::bind_texture_unit(unit, texture) }
+    }
+
+    fn set_depth_test(&mut self, enabled: bool) {
+        unsafe {
+            if enabled {
+                c::enable_depth_test();
+            } else {
+                c::disable_depth_test();
+            }
+        }
+    }
+
+    fn set_viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        unsafe { c::viewport(x, y, width, height) }
+    }
+
+    fn window_size(&self) -> Vec2i {
+        unsafe { c::get_window_size() }
+    }
+
+    fn target_size(&self) -> Vec2i {
+        unsafe { c::get_target_size() }
+    }
+
+    fn draw_arrays(&mut self, primitive: Primitive, first: u32, count: u32) {
+        unsafe { c::draw_arrays(primitive as u32, first as i32, count as i32) }
+    }
+
+    fn draw_elements(&mut self, primitive: Primitive, first: u32, count: u32, kind: IndexType) {
+        let offset = first as usize * kind.size();
+        unsafe { c::draw_elements(primitive as u32, count as i32, gl_index_type(kind), offset) }
+    }
+
+    fn draw_arrays_instanced(
+        &mut self,
+        primitive: Primitive,
+        first: u32,
+        count: u32,
+        instances: u32,
+    ) {
+        unsafe {
+            c::draw_arrays_instanced(
+                primitive as u32,
+                first as i32,
+                count as i32,
+                instances as i32,
+            )
+        }
+    }
+
+    fn draw_elements_instanced(
+        &mut self,
+        primitive: Primitive,
+        first: u32,
+        count: u32,
+        kind: IndexType,
+        instances: u32,
+    ) {
+        let offset = first as usize * kind.size();
+        unsafe {
+            c::draw_elements_instanced(
+                primitive as u32,
+                count as i32,
+                gl_index_type(kind),
+                offset,
+                instances as i32,
+            )
+        }
+    }
+
+    fn draw_triangle_arrays(&mut self, vertex_count: u32) {
+        unsafe { c::draw_triangle_arrays(vertex_count) }
+    }
+}
+
+/// One call made on a `RecordingBackend`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    CreateProgram {
+        id: u32,
+        vertex_source: String,
+        fragment_source: String,
+    },
+    CreateVertexArray(u32),
+    CreateBuffer(u32),
+    CreateTexture(u32),
+    CreateFramebuffer(u32),
+    DeleteProgram(u32),
+    DeleteVertexArray(u32),
+    DeleteBuffer(u32),
+    DeleteTexture(u32),
+    DeleteFramebuffer(u32),
+    BindProgram(u32),
+    BindVertexArray(u32),
+    BindBuffer(u32),
+    BindTexture(u32),
+    BindElementBuffer(u32),
+    BindFramebuffer(u32),
+    AttachTexture {
+        attachment: Attachment,
+        texture: u32,
+    },
+    SetDrawBuffers(u32),
+    VertexAttribPointerFloat {
+        index: u32,
+        count: i32,
+        stride: u32,
+        offset: usize,
+    },
+    VertexAttribDivisor {
+        index: u32,
+        divisor: u32,
+    },
+    UpdateVertices(Vec<f32>),
+    UpdateVerticesStatic(Vec<f32>),
+    /// Indices widened to `u32`, with the type they were uploaded as.
+    UpdateIndices {
+        kind: IndexType,
+        indices: Vec<u32>,
+    },
+    TexImage2d {
+        width: i32,
+        height: i32,
+        format: PixelFormat,
+    },
+    GenerateMipmaps,
+    SetUniform {
+        program: u32,
+        name: String,
+        value: Uniform,
+    },
+    BindTextureUnit {
+        unit: u32,
+        texture: u32,
+    },
+    SetDepthTest(bool),
+    SetViewport {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    DrawArrays {
+        primitive: Primitive,
+        first: u32,
+        count: u32,
+    },
+    DrawElements {
+        primitive: Primitive,
+        first: u32,
+        count: u32,
+        kind: IndexType,
+    },
+    DrawArraysInstanced {
+        primitive: Primitive,
+        first: u32,
+        count: u32,
+        instances: u32,
+    },
+    DrawElementsInstanced {
+        primitive: Primitive,
+        first: u32,
+        count: u32,
+        kind: IndexType,
+        instances: u32,
+    },
+}
+
+/// A draw call together with the state bound when it was issued.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedDraw {
+    /// 0 for the window.
+    pub framebuffer: u32,
+    pub program: u32,
+    pub vertex_array: u32,
+    pub texture: u32,
+    pub depth_test: bool,
+    pub primitive: Primitive,
+    /// Vertices drawn, or indices for indexed draws.
+    pub vertex_count: u32,
+    /// 1 for draws that are not instanced.
+    pub instance_count: u32,
+    /// Uniforms set on `program` so far, by name.
+    pub uniforms: Vec<(String, Uniform)>,
+    /// Contents of the buffer the vertex array's attributes read, or of
+    /// the bound buffer if none were set, at the time of the draw.
+    pub vertices: Vec<f32>,
+    /// The indices read by an indexed draw, `None` for array draws.
+    pub indices: Option<Vec<u32>>,
+    /// Contents of the buffer per-instance attributes read, if any.
+    pub instance_data: Option<Vec<f32>>,
+}
+
+#[derive(Debug)]
+struct Recording {
+    commands: Vec<Command>,
+    window_size: Vec2i,
+    /// Size passed with the bound framebuffer, `None` for the window.
+    target_size: Option<Vec2i>,
+    next_id: u32,
+    /// Uniform names declared in each program's sources.
+    declared_uniforms: Vec<(u32, Vec<String>)>,
+    /// (program, name) of each location handed out, indexed by location.
+    locations: Vec<(u32, String)>,
+}
+
+/// Names declared with `uniform <type> <name>;` in GLSL source.
+fn declared_uniforms(source: &str) -> impl Iterator<Item = String> + '_ {
+    source.lines().filter_map(|line| {
+        let declaration = line.trim().strip_prefix("uniform ")?;
+        let declaration = declaration.split(';').next()?;
+        let name = declaration.split_whitespace().last()?;
+        Some(name.split('[').next()?.to_string())
+    })
+}
+
+/// Records calls instead of executing them. Ids are handed out from 1 in
+/// creation order, shared by all kinds of object. Clones share one log.
+#[derive(Clone, Debug)]
+pub struct RecordingBackend {
+    recording: Rc<RefCell<Recording>>,
+}
+
+impl RecordingBackend {
+    pub fn new(window_width: i32, window_height: i32) -> Self {
+        RecordingBackend {
+            recording: Rc::new(RefCell::new(Recording {
+                commands: vec![],
+                window_size: Vec2i {
+                    x: window_width,
+                    y: window_height,
+                },
+                target_size: None,
+                next_id: 1,
+                declared_uniforms: vec![],
+                locations: vec![],
+            })),
+        }
+    }
+
+    /// Change the size reported by `window_size`.
+    pub fn set_window_size(&self, width: i32, height: i32) {
+        self.recording.borrow_mut().window_size = Vec2i {
+            x: width,
+            y: height,
+        };
+    }
+
+    /// The commands recorded so far.
+    pub fn commands(&self) -> Vec<Command> {
+        self.recording.borrow().commands.clone()
+    }
+
+    pub fn clear_commands(&self) {
+        self.recording.borrow_mut().commands.clear();
+    }
+
+    fn push(&self, command: Command) {
+        self.recording.borrow_mut().commands.push(command);
+    }
+
+    fn create(&self, command: impl FnOnce(u32) -> Command) -> u32 {
+        let mut recording = self.recording.borrow_mut();
+        let id = recording.next_id;
+        recording.next_id += 1;
+        recording.commands.push(command(id));
+        id
+    }
+
+    /// Replay the recorded commands to reconstruct each draw call.
+    pub fn draws(&self) -> Vec<RecordedDraw> {
+        let recording = self.recording.borrow();
+        let mut draws = vec![];
+        let (mut program, mut vertex_array, mut buffer, mut texture) = (0, 0, 0, 0);
+        let mut framebuffer = 0;
+        let mut depth_test = false;
+        let mut buffers: Vec<(u32, &[f32])> = vec![];
+        // (vertex array, index, buffer, divisor) of each attribute set.
+        let mut attributes: Vec<(u32, u32, u32, u32)> = vec![];
+        // Element buffer bound to each vertex array, and each one's indices.
+        let mut element_buffers: Vec<(u32, u32)> = vec![];
+        let mut index_buffers: Vec<(u32, &[u32])> = vec![];
+        let mut uniforms: Vec<(u32, &str, &Uniform)> = vec![];
+        for command in &recording.commands {
+            let element_buffer = element_buffers
+                .iter()
+                .find(|(vao, _)| *vao == vertex_array)
+                .map_or(0, |(_, ebo)| *ebo);
+            let attribute_buffer = |instanced: bool| {
+                attributes
+                    .iter()
+                    .filter(|(vao, _, _, divisor)| {
+                        *vao == vertex_array && (*divisor > 0) == instanced
+                    })
+                    .min_by_key(|(_, index, _, _)| *index)
+                    .map(|(_, _, vbo, _)| *vbo)
+            };
+            let contents = |id: u32| {
+                buffers
+                    .iter()
+                    .find(|(vbo, _)| *vbo == id)
+                    .map_or(vec![], |(_, v)| v.to_vec())
+            };
+            let vertices = contents(attribute_buffer(false).unwrap_or(buffer));
+            let instance_data = attribute_buffer(true).map(contents);
+            let indices = |first: u32, count: u32| {
+                let indices = index_buffers
+                    .iter()
+                    .find(|(id, _)| *id == element_buffer)
+                    .map_or(&[][..], |(_, indices)| indices);
+                let start = (first as usize).min(indices.len());
+                let end = (start + count as usize).min(indices.len());
+                Some(indices[start..end].to_vec())
+            };
+            let draw = |primitive: Primitive,
+                        vertex_count: u32,
+                        instance_count: u32,
+                        indices: Option<Vec<u32>>| {
+                draws.push(RecordedDraw {
+                    framebuffer,
+                    program,
+                    vertex_array,
+                    texture,
+                    depth_test,
+                    primitive,
+                    vertex_count,
+                    instance_count,
+                    uniforms: uniforms
+                        .iter()
+                        .filter(|(p, _, _)| *p == program)
+                        .map(|(_, n, v)| (n.to_string(), (*v).clone()))
+                        .collect(),
+                    vertices,
+                    indices,
+                    instance_data,
+                })
+            };
+            match command {
+                Command::BindProgram(id) => program = *id,
+                Command::BindVertexArray(id) => vertex_array = *id,
+                Command::BindBuffer(id) => buffer = *id,
+                Command::BindTexture(id) => texture = *id,
+                Command::BindFramebuffer(id) => framebuffer = *id,
+                Command::SetDepthTest(enabled) => depth_test = *enabled,
+                Command::SetUniform {
+                    program,
+                    name,
+                    value,
+                } => {
+                    uniforms.retain(|(p, n, _)| (*p, *n) != (*program, name.as_str()));
+                    uniforms.push((*program, name, value));
+                }
+                Command::VertexAttribPointerFloat { index, .. } => {
+                    let divisor = attributes
+                        .iter()
+                        .find(|(vao, i, _, _)| (*vao, *i) == (vertex_array, *index))
+                        .map_or(0, |(_, _, _, divisor)| *divisor);
+                    attributes.retain(|(vao, i, _, _)| (*vao, *i) != (vertex_array, *index));
+                    attributes.push((vertex_array, *index, buffer, divisor));
+                }
+                Command::VertexAttribDivisor { index, divisor } => {
+                    for attribute in &mut attributes {
+                        if (attribute.0, attribute.1) == (vertex_array, *index) {
+                            attribute.3 = *divisor;
+                        }
+                    }
+                }
+                Command::UpdateVertices(vertices) | Command::UpdateVerticesStatic(vertices) => {
+                    buffers.retain(|(id, _)| *id != buffer);
+                    buffers.push((buffer, vertices));
+                }
+                Command::BindElementBuffer(id) => {
+                    element_buffers.retain(|(vao, _)| *vao != vertex_array);
+                    element_buffers.push((vertex_array, *id));
+                }
+                Command::UpdateIndices { indices, .. } => {
+                    index_buffers.retain(|(id, _)| *id != element_buffer);
+                    index_buffers.push((element_buffer, indices));
+                }
+                Command::DrawArrays {
+                    primitive, count, ..
+                } => draw(*primitive, *count, 1, None),
+                Command::DrawElements {
+                    primitive,
+                    first,
+                    count,
+                    ..
+                } => draw(*primitive, *count, 1, indices(*first, *count)),
+                Command::DrawArraysInstanced {
+                    primitive,
+                    count,
+                    instances,
+                    ..
+                } => draw(*primitive, *count, *instances, None),
+                Command::DrawElementsInstanced {
+                    primitive,
+                    first,
+                    count,
+                    instances,
+                    ..
+                } => draw(*primitive, *count, *instances, indices(*first, *count)),
+                _ => {}
+            }
+        }
+        draws
+    }
+}
+
+impl Default for RecordingBackend {
+    fn default() -> Self {
+        RecordingBackend::new(800, 600)
+    }
+}
+
+impl RenderBackend for RecordingBackend {
+    fn create_program(
+        &mut self,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Result<u32, EngineError> {
+        let id = self.create(|id| Command::CreateProgram {
+            id,
+            vertex_source: vertex_source.to_string(),
+            fragment_source: fragment_source.to_string(),
+        });
+        let names = declared_uniforms(vertex_source)
+            .chain(declared_uniforms(fragment_source))
+            .collect();
+        self.recording
+            .borrow_mut()
+            .declared_uniforms
+            .push((id, names));
+        Ok(id)
+    }
+
+    fn create_vertex_array(&mut self) -> u32 {
+        self.create(Command::CreateVertexArray)
+    }
+
+    fn create_buffer(&mut self) -> u32 {
+        self.create(Command::CreateBuffer)
+    }
+
+    fn create_texture(&mut self) -> u32 {
+        self.create(Command::CreateTexture)
+    }
+
+    fn create_framebuffer(&mut self) -> u32 {
+        self.create(Command::CreateFramebuffer)
+    }
+
+    fn delete_program(&mut self, program: u32) {
+        self.push(Command::DeleteProgram(program));
+    }
+
+    fn delete_vertex_array(&mut self, vao: u32) {
+        self.push(Command::DeleteVertexArray(vao));
+    }
+
+    fn delete_buffer(&mut self, vbo: u32) {
+        self.push(Command::DeleteBuffer(vbo));
+    }
+
+    fn delete_texture(&mut self, texture: u32) {
+        self.push(Command::DeleteTexture(texture));
+    }
+
+    fn delete_framebuffer(&mut self, fbo: u32) {
+        self.push(Command::DeleteFramebuffer(fbo));
+    }
+
+    fn bind_program(&mut self, program: u32) {
+        self.push(Command::BindProgram(program));
+    }
+
+    fn bind_vertex_array(&mut self, vao: u32) {
+        self.push(Command::BindVertexArray(vao));
+    }
+
+    fn bind_buffer(&mut self, vbo: u32) {
+        self.push(Command::BindBuffer(vbo));
+    }
+
+    fn bind_texture(&mut self, texture: u32) {
+        self.push(Command::BindTexture(texture));
+    }
+
+    fn bind_element_buffer(&mut self, ebo: u32) {
+        self.push(Command::BindElementBuffer(ebo));
+    }
+
+    fn bind_framebuffer(&mut self, fbo: u32, size: Vec2i) {
+        self.recording.borrow_mut().target_size = if fbo == 0 { None } else { Some(size) };
+        self.push(Command::BindFramebuffer(fbo));
+    }
+
+    fn attach_texture(&mut self, attachment: Attachment, texture: u32) {
+        self.push(Command::AttachTexture {
+            attachment,
+            texture,
+        });
+    }
+
+    fn set_draw_buffers(&mut self, count: u32) {
+        self.push(Command::SetDrawBuffers(count));
+    }
+
+    /// Always complete; the recording does not validate attachments.
+    fn framebuffer_complete(&mut self) -> bool {
+        true
+    }
+
+    fn vertex_attrib_pointer_float(&mut self, index: u32, count: i32, stride: u32, offset: usize) {
+        self.push(Command::VertexAttribPointerFloat {
+            index,
+            count,
+            stride,
+            offset,
+        });
+    }
+
+    fn vertex_attrib_divisor(&mut self, index: u32, divisor: u32) {
+        self.push(Command::VertexAttribDivisor { index, divisor });
+    }
+
+    fn update_vertices_dynamic(&mut self, vertices: &[f32]) {
+        self.push(Command::UpdateVertices(vertices.to_vec()));
+    }
+
+    fn update_vertices_static(&mut self, vertices: &[f32]) {
+        self.push(Command::UpdateVerticesStatic(vertices.to_vec()));
+    }
+
+    fn update_indices_dynamic(&mut self, indices: Indices) {
+        let (kind, indices) = match indices {
+            Indices::U16(i) => (IndexType::U16, i.iter().map(|&i| i as u32).collect()),
+            Indices::U32(i) => (IndexType::U32, i.to_vec()),
+        };
+        self.push(Command::UpdateIndices { kind, indices });
+    }
+
+    fn tex_image_2d(&mut self, texture: &Texture) {
+        self.push(Command::TexImage2d {
+            width: texture.width,
+            height: texture.height,
+            format: texture.format,
+        });
+    }
+
+    fn generate_mipmaps(&mut self) {
+        self.push(Command::GenerateMipmaps);
+    }
+
+    fn uniform_location(&mut self, program: u32, name: &str) -> Option<i32> {
+        let mut recording = self.recording.borrow_mut();
+        // Like GL, "lights[0]" finds the array "lights".
+        let name = name.split('[').next().unwrap_or(name);
+        let declared = recording
+            .declared_uniforms
+            .iter()
+            .any(|(id, names)| *id == program && names.iter().any(|n| n == name));
+        if !declared {
+            return None;
+        }
+        let key = (program, name.to_string());
+        let location = match recording.locations.iter().position(|l| *l == key) {
+            Some(i) => i,
+            None => {
+                recording.locations.push(key);
+                recording.locations.len() - 1
+            }
+        };
+        Some(location as i32)
+    }
+
+    fn set_uniform(&mut self, location: i32, value: &Uniform) {
+        let target = self
+            .recording
+            .borrow()
+            .locations
+            .get(location as usize)
+            .cloned();
+        if let Some((program, name)) = target {
+            self.push(Command::SetUniform {
+                program,
+                name,
+                value: value.clone(),
+            });
+        }
+    }
+
+    fn bind_texture_unit(&mut self, unit: u32, texture: u32) {
+        self.push(Command::BindTextureUnit { unit, texture });
+    }
+
+    fn set_depth_test(&mut self, enabled: bool) {
+        self.push(Command::SetDepthTest(enabled));
+    }
+
+    fn set_viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        self.push(Command::SetViewport {
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+
+    fn window_size(&self) -> Vec2i {
+        self.recording.borrow().window_size
+    }
+
+    fn target_size(&self) -> Vec2i {
+        let recording = self.recording.borrow();
+        recording.target_size.unwrap_or(recording.window_size)
+    }
+
+    fn draw_arrays(&mut self, primitive: Primitive, first: u32, count: u32) {
+        self.push(Command::DrawArrays {
+            primitive,
+            first,
+            count,
+        });
+    }
+
+    fn draw_elements(&mut self, primitive: Primitive, first: u32, count: u32, kind: IndexType) {
+        self.push(Command::DrawElements {
+            primitive,
+            first,
+            count,
+            kind,
+        });
+    }
+
+    fn draw_arrays_instanced(
+        &mut self,
+        primitive: Primitive,
+        first: u32,
+        count: u32,
+        instances: u32,
+    ) {
+        self.push(Command::DrawArraysInstanced {
+            primitive,
+            first,
+            count,
+            instances,
+        });
+    }
+
+    fn draw_elements_instanced(
+        &mut self,
+        primitive: Primitive,
+        first: u32,
+        count: u32,
+        kind: IndexType,
+        instances: u32,
+    ) {
+        self.push(Command::DrawElementsInstanced {
+            primitive,
+            first,
+            count,
+            kind,
+            instances,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpu_mesh::GpuMesh;
+    use crate::lit_renderer::{LitInstance, LitRenderer};
+    use crate::render_target::RenderTarget;
+    use crate::simple_mesh::SimpleMesh;
+    use crate::texture_renderer::TextureRenderer;
+    use math::{mat4::*, rect::*, vec3::*};
+
+    fn full_rect() -> Rect {
+        Rect {
+            x: 10.0,
+            y: 20.0,
+            w: 30.0,
+            h: 40.0,
+        }
+    }
+
+    #[test]
+    fn records_texture_renderer_draws() {
+        let backend = RecordingBackend::new(800, 600);
+        let mut renderer = TextureRenderer::with_backend(backend.clone()).unwrap();
+        renderer.update_texture(&Texture::new(2, 2, PixelFormat::RGBA8));
+        renderer.draw_full_texture(&full_rect());
+        renderer.render();
+
+        let draws = backend.draws();
+        assert_eq!(draws.len(), 1);
+        let draw = &draws[0];
+        assert_eq!(draw.framebuffer, 0);
+        assert_eq!(draw.primitive, Primitive::Triangles);
+        assert_eq!(draw.vertex_count, 6);
+        assert_eq!(draw.instance_count, 1);
+        assert_ne!(draw.texture, 0);
+        // Two floats of position and two of uv per vertex.
+        assert_eq!(draw.vertices.len(), 6 * 4);
+        assert_eq!(&draw.vertices[..4], &[10.0, 20.0, 0.0, 0.0]);
+        assert!(draw.uniforms.iter().any(|(name, _)| name == "view"));
+        assert!(backend.commands().contains(&Command::TexImage2d {
+            width: 2,
+            height: 2,
+            format: PixelFormat::RGBA8,
+        }));
+    }
+
+    #[test]
+    fn clears_the_queue_after_render() {
+        let backend = RecordingBackend::new(800, 600);
+        let mut renderer = TextureRenderer::with_backend(backend.clone()).unwrap();
+        renderer.draw_full_texture(&full_rect());
+        renderer.render();
+        renderer.render();
+        let counts: Vec<_> = backend.draws().iter().map(|d| d.vertex_count).collect();
+        assert_eq!(counts, [6, 0]);
+    }
+
+    #[test]
+    fn records_instanced_mesh_draws() {
+        let backend = RecordingBackend::new(800, 600);
+        let mut renderer = LitRenderer::with_backend(backend.clone()).unwrap();
+        let mut cube = SimpleMesh::new();
+        cube.add_cube(Mat4::IDENTITY);
+        let mut mesh = GpuMesh::from_simple_mesh(&backend, &cube, Vec2::new(0.5, 0.5));
+        let instances = [
+            LitInstance {
+                model: Mat4::IDENTITY,
+                color: Color::white(),
+            },
+            LitInstance {
+                model: Mat4::translate(Vec3::new(2.0, 0.0, 0.0)),
+                color: Color::black(),
+            },
+        ];
+        renderer.draw_mesh_instanced(&mut mesh, &instances);
+
+        let draws = backend.draws();
+        assert_eq!(draws.len(), 1);
+        assert_eq!(draws[0].vertex_count, mesh.len() as u32);
+        assert_eq!(draws[0].instance_count, 2);
+        assert!(draws[0].depth_test);
+        assert!(
+            draws[0]
+                .uniforms
+                .contains(&("instanced".to_string(), Uniform::Bool(true)))
+        );
+        // A 4x4 matrix and a color per instance.
+        assert_eq!(draws[0].instance_data.as_ref().map(Vec::len), Some(2 * 20));
+    }
+
+    #[test]
+    fn records_render_target_framebuffer() {
+        let backend = RecordingBackend::new(800, 600);
+        let mut target =
+            RenderTarget::with_backend(backend.clone(), 64, 32, &[PixelFormat::RGBA8], true)
+                .unwrap();
+        let mut renderer = TextureRenderer::with_backend(backend.clone()).unwrap();
+        target.bind();
+        assert_eq!(backend.target_size(), Vec2i { x: 64, y: 32 });
+        renderer.draw_full_texture(&full_rect());
+        renderer.render();
+        target.unbind();
+        assert_eq!(backend.target_size(), Vec2i { x: 800, y: 600 });
+        renderer.draw_render_target(&full_rect());
+        renderer.render_with(target.color(0).unwrap());
+
+        let draws = backend.draws();
+        assert_eq!(draws.len(), 2);
+        assert_ne!(draws[0].framebuffer, 0);
+        assert_eq!(draws[1].framebuffer, 0);
+        assert_eq!(draws[1].texture, target.color(0).unwrap().id());
+    }
+}
@@ -17,6 +17,29 @@ pub struct FontData {
     pub baked_chars: *mut BakedChar,
 }
 
+// Status codes of `initialize`, `initialize_headless` and `create_font_data`.
+pub const ENGINE_OK: i32 = 0;
+pub const ENGINE_GLFW_INIT_FAILED: i32 = 1;
+pub const ENGINE_WINDOW_FAILED: i32 = 2;
+pub const ENGINE_GL_LOAD_FAILED: i32 = 3;
+pub const ENGINE_FRAMEBUFFER_INCOMPLETE: i32 = 4;
+pub const ENGINE_FONT_NOT_FOUND: i32 = 5;
+pub const ENGINE_FONT_INVALID: i32 = 6;
+pub const ENGINE_FONT_ATLAS_TOO_SMALL: i32 = 7;
+
+// `ProgramResult::failed_stage` values.
+pub const STAGE_NONE: i32 = 0;
+pub const STAGE_VERTEX: i32 = 1;
+pub const STAGE_FRAGMENT: i32 = 2;
+
+#[repr(C)]
+pub struct ProgramResult {
+    pub program: u32,
+    pub failed_stage: i32,
+    /// Owned by the caller; release with `free_string`.
+    pub info_log: *mut c_char,
+}
+
 unsafe extern "C" {
     pub fn get_char() -> u32;
     pub fn is_key_pressed(key: i32) -> i32;
@@ -30,35 +53,75 @@ unsafe extern "C" {
     pub fn enable_depth_test();
     pub fn disable_depth_test();
     pub fn cull_back_faces();
-    pub fn initialize(screen_width: i32, screen_height: i32);
-    pub fn initialize_program(vertex_souce: *const c_char, fragment_source: *const c_char) -> u32;
+    pub fn initialize(screen_width: i32, screen_height: i32) -> i32;
+    pub fn initialize_headless(width: i32, height: i32) -> i32;
+    pub fn read_pixels(x: i32, y: i32, width: i32, height: i32, out: *mut u8);
+    pub fn initialize_program(
+        vertex_souce: *const c_char,
+        fragment_source: *const c_char,
+    ) -> ProgramResult;
+    pub fn get_last_error() -> *const c_char;
+    pub fn free_string(str: *mut c_char);
     pub fn create_vao() -> u32;
     pub fn create_vbo() -> u32;
-    pub fn set_matrix4(program: u32, name: *const c_char, ptr: *const f32);
-    pub fn set_vector3(program: u32, name: *const c_char, x: f32, y: f32, z: f32);
+    pub fn delete_program(program: u32);
+    pub fn delete_vao(vao: u32);
+    pub fn delete_vbo(vbo: u32);
+    pub fn delete_texture(texture: u32);
+    pub fn create_framebuffer() -> u32;
+    pub fn delete_framebuffer(fbo: u32);
+    pub fn bind_framebuffer(fbo: u32, width: i32, height: i32);
+    pub fn framebuffer_texture(index: i32, texture: u32);
+    pub fn draw_buffers(count: i32);
+    pub fn framebuffer_complete() -> i32;
+    pub fn free_font_data(fontdata: FontData);
+    pub fn terminate();
+    pub fn get_uniform_location(program: u32, name: *const c_char) -> i32;
+    pub fn set_uniform_floats(location: i32, components: i32, count: i32, values: *const f32);
+    pub fn set_uniform_ints(location: i32, count: i32, values: *const i32);
+    pub fn set_uniform_matrix3(location: i32, count: i32, values: *const f32);
+    pub fn set_uniform_matrix4(location: i32, count: i32, values: *const f32);
+    pub fn bind_texture_unit(unit: u32, texture: u32);
     pub fn bind_program(program: u32);
     pub fn bind_vao(vao: u32);
     pub fn bind_vbo(vbo: u32);
+    pub fn bind_ebo(ebo: u32);
     pub fn bind_texture(texture: u32);
     pub fn create_font_data(
         ttf_path: *const c_char,
         pixel_height: f32,
         atlas_width: i32,
         atlas_height: i32,
-    ) -> FontData;
+        out: *mut FontData,
+        chars_baked: *mut i32,
+    ) -> i32;
     pub fn viewport(x: i32, y: i32, w: i32, h: i32);
     pub fn clear(bits: i32);
     pub fn clear_color(r: f32, g: f32, b: f32, a: f32);
     pub fn draw_triangle_arrays(num_vertices: u32);
+    pub fn draw_arrays(mode: u32, first: i32, count: i32);
+    pub fn draw_elements(mode: u32, count: i32, kind: u32, offset: usize);
+    pub fn draw_arrays_instanced(mode: u32, first: i32, count: i32, instances: i32);
+    pub fn draw_elements_instanced(mode: u32, count: i32, kind: u32, offset: usize, instances: i32);
     pub fn swap_buffers();
     pub fn poll_events();
     pub fn create_texture() -> u32;
-    pub fn tex_image_2d(ptr: *const u8, width: i32, height: i32, channels: i32);
+    pub fn tex_image_2d(
+        ptr: *const u8,
+        width: i32,
+        height: i32,
+        internal_format: i32,
+        format: u32,
+        kind: u32,
+    );
     pub fn generate_mipmap_2d();
     pub fn update_vertices_static(ptr: *const u8, size: u32);
     pub fn update_vertices_dynamic(ptr: *const u8, size: u32);
+    pub fn update_indices_dynamic(ptr: *const u8, size: u32);
     pub fn vertex_attrib_pointer_float(id: u32, count: i32, stride: u32, ptr: usize);
+    pub fn vertex_attrib_divisor(id: u32, divisor: u32);
     pub fn enable_transparency();
     pub fn window_should_close() -> i32;
     pub fn get_window_size() -> math::Vec2i;
+    pub fn get_target_size() -> math::Vec2i;
 }
@@ -0,0 +1,119 @@
+//! Errors from context creation, render targets, shader compilation and
+//! font loading.
+
+use crate::render_target::MAX_COLOR_ATTACHMENTS;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Link,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EngineError {
+    /// GLFW failed to initialize; carries GLFW's own description.
+    GlfwInit(String),
+    /// No window or offscreen context could be created.
+    Context(String),
+    /// The OpenGL function pointers could not be loaded.
+    GlLoad,
+    /// The offscreen framebuffer of a headless context or a `RenderTarget`
+    /// is incomplete.
+    FramebufferIncomplete,
+    /// A `RenderTarget` asked for more than `MAX_COLOR_ATTACHMENTS`.
+    TooManyColorAttachments(usize),
+    /// A shader failed to compile or the program failed to link.
+    /// `line` is the source line of the first error, when the driver's
+    /// info log gives one.
+    Shader {
+        stage: ShaderStage,
+        line: Option<u32>,
+        log: String,
+    },
+    FontNotFound(String),
+    /// The file is not a TrueType font.
+    InvalidFont(String),
+    /// Only `chars_baked` characters fit in the atlas.
+    FontAtlasTooSmall {
+        path: String,
+        width: i32,
+        height: i32,
+        chars_baked: i32,
+    },
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::GlfwInit(msg) => write!(f, "failed to initialize GLFW: {}", msg),
+            EngineError::Context(msg) => write!(f, "failed to create a GL context: {}", msg),
+            EngineError::GlLoad => write!(f, "failed to load OpenGL functions"),
+            EngineError::FramebufferIncomplete => write!(f, "framebuffer is incomplete"),
+            EngineError::TooManyColorAttachments(count) => write!(
+                f,
+                "{} color attachments requested, at most {} are supported",
+                count, MAX_COLOR_ATTACHMENTS
+            ),
+            EngineError::Shader { stage, line, log } => {
+                match stage {
+                    ShaderStage::Vertex => write!(f, "vertex shader failed to compile")?,
+                    ShaderStage::Fragment => write!(f, "fragment shader failed to compile")?,
+                    ShaderStage::Link => write!(f, "shader program failed to link")?,
+                }
+                if let Some(line) = line {
+                    write!(f, " at line {}", line)?;
+                }
+                write!(f, ":\n{}", log.trim_end())
+            }
+            EngineError::FontNotFound(path) => write!(f, "cannot read font {}", path),
+            EngineError::InvalidFont(path) => write!(f, "{} is not a valid font", path),
+            EngineError::FontAtlasTooSmall {
+                path,
+                width,
+                height,
+                chars_baked,
+            } => write!(
+                f,
+                "{}x{} atlas only fits {} characters of {}",
+                width, height, chars_baked, path
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+/// First `file:line` or `file(line)` location in a line of an info log.
+/// This covers the Mesa (`0:12(5):`), NVIDIA (`0(12) :`) and AMD/Intel
+/// (`ERROR: 0:12:`) formats.
+fn location(line: &str) -> Option<u32> {
+    let b = line.as_bytes();
+    let mut i = 0;
+    while i < b.len() {
+        if !b[i].is_ascii_digit() || (i > 0 && b[i - 1].is_ascii_alphanumeric()) {
+            i += 1;
+            continue;
+        }
+        while i < b.len() && b[i].is_ascii_digit() {
+            i += 1;
+        }
+        if i < b.len() && (b[i] == b':' || b[i] == b'(') {
+            let start = i + 1;
+            let end = start + b[start..].iter().take_while(|c| c.is_ascii_digit()).count();
+            if end > start {
+                return line[start..end].parse().ok();
+            }
+        }
+    }
+    None
+}
+
+/// Source line of the first error in a shader info log.
+pub(crate) fn error_line(log: &str) -> Option<u32> {
+    log.lines()
+        .filter(|l| l.to_ascii_lowercase().contains("error"))
+        .find_map(location)
+        .or_else(|| log.lines().find_map(location))
+}
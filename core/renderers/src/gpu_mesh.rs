@@ -0,0 +1,83 @@
+use crate::backend::*;
+use crate::handles::*;
+use crate::lit_renderer::{INSTANCE_LOCATION, LitInstance, LitVertex};
+use crate::simple_mesh::SimpleMesh;
+use math::{mat4::*, *};
+
+/// Lit vertices uploaded once into their own vertex array, to be drawn
+/// any number of times with `LitRenderer::draw_mesh`, or many times in
+/// one call with `LitRenderer::draw_mesh_instanced`.
+pub struct GpuMesh<B: RenderBackend = GlBackend> {
+    vao: VertexArray<B>,
+    vbo: VertexBuffer<LitVertex, B>,
+    /// Instances of the last instanced draw. Never empty, so the instance
+    /// attributes stay readable in non-instanced draws.
+    instances: VertexBuffer<LitInstance, B>,
+    /// CPU copy for `LitRenderer::draw_mesh_cpu`.
+    vertices: Vec<LitVertex>,
+}
+
+impl<B: RenderBackend> GpuMesh<B> {
+    /// Upload a triangle list.
+    pub fn new(backend: &B, vertices: Vec<LitVertex>) -> Self {
+        let mut vao = VertexArray::new(backend);
+        let mut vbo = VertexBuffer::new(backend);
+        vao.set_layout(&vbo);
+        vbo.upload_static(&vertices);
+        let mut instances = VertexBuffer::new(backend);
+        instances.upload(&[LitInstance {
+            model: Mat4::IDENTITY,
+            color: Color::white(),
+        }]);
+        vao.set_instance_layout(&instances, INSTANCE_LOCATION);
+        GpuMesh {
+            vao,
+            vbo,
+            instances,
+            vertices,
+        }
+    }
+
+    /// Upload `mesh` with flat normals, every vertex sampling the texture
+    /// at `uv`, as `LitRenderer::draw_simple_mesh` queues it.
+    pub fn from_simple_mesh(backend: &B, mesh: &SimpleMesh, uv: Vec2) -> Self {
+        GpuMesh::new(backend, simple_mesh_vertices(mesh, uv))
+    }
+
+    pub fn vertices(&self) -> &[LitVertex] {
+        &self.vertices
+    }
+
+    /// Number of vertices.
+    pub fn len(&self) -> usize {
+        self.vbo.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vbo.is_empty()
+    }
+
+    pub(crate) fn vertex_array(&self) -> &VertexArray<B> {
+        &self.vao
+    }
+
+    pub(crate) fn upload_instances(&mut self, instances: &[LitInstance]) {
+        self.instances.upload(instances);
+    }
+}
+
+/// The triangles of `mesh` as lit vertices with flat normals.
+pub fn simple_mesh_vertices(mesh: &SimpleMesh, uv: Vec2) -> Vec<LitVertex> {
+    let mut vertices = Vec::with_capacity(mesh.triangles.len() * 3);
+    for t in &mesh.triangles {
+        let normal = t.normal();
+        for position in [t.a, t.b, t.c] {
+            vertices.push(LitVertex {
+                position,
+                normal,
+                uv,
+            });
+        }
+    }
+    vertices
+}
@@ -0,0 +1,375 @@
+//! Owned GPU and font resources that are released on drop.
+//!
+//! GPU handles keep a clone of the backend that created them. Dropping a
+//! GL handle after `terminate` is a no-op, since the context is gone.
+
+use crate::backend::*;
+use crate::c;
+use crate::error::EngineError;
+use crate::helper_functions::*;
+use crate::uniform::Uniform;
+use crate::vertex::VertexLayout;
+use math::texture::{PixelFormat, Texture};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// A linked shader program and the uniform locations looked up so far.
+pub struct Program<B: RenderBackend = GlBackend> {
+    id: u32,
+    backend: B,
+    locations: HashMap<String, Option<i32>>,
+}
+
+impl<B: RenderBackend> Program<B> {
+    pub fn new(
+        backend: &B,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Result<Self, EngineError> {
+        let mut backend = backend.clone();
+        let id = backend.create_program(vertex_source, fragment_source)?;
+        Ok(Program {
+            id,
+            backend,
+            locations: HashMap::new(),
+        })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Location of uniform `name`, cached after the first lookup. A name
+    /// the program does not have prints a warning once and gives `None`;
+    /// note that drivers drop uniforms the shaders never use.
+    pub fn location(&mut self, name: &str) -> Option<i32> {
+        if let Some(&location) = self.locations.get(name) {
+            return location;
+        }
+        let location = self.backend.uniform_location(self.id, name);
+        if location.is_none() {
+            eprintln!("warning: program {} has no uniform \"{}\"", self.id, name);
+        }
+        self.locations.insert(name.to_string(), location);
+        location
+    }
+
+    /// Set uniform `name`, binding the program first since GL sets
+    /// uniforms on the bound one. Unknown names are skipped.
+    pub fn set_uniform(&mut self, name: &str, value: impl Into<Uniform>) {
+        if let Some(location) = self.location(name) {
+            self.backend.bind_program(self.id);
+            self.backend.set_uniform(location, &value.into());
+        }
+    }
+}
+
+impl<B: RenderBackend> Drop for Program<B> {
+    fn drop(&mut self) {
+        self.backend.delete_program(self.id);
+    }
+}
+
+/// A vertex buffer object.
+pub struct Buffer<B: RenderBackend = GlBackend> {
+    id: u32,
+    backend: B,
+}
+
+impl<B: RenderBackend> Buffer<B> {
+    pub fn new(backend: &B) -> Self {
+        let mut backend = backend.clone();
+        let id = backend.create_buffer();
+        Buffer { id, backend }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl<B: RenderBackend> Drop for Buffer<B> {
+    fn drop(&mut self) {
+        self.backend.delete_buffer(self.id);
+    }
+}
+
+/// A vertex buffer holding vertices of type `T`.
+pub struct VertexBuffer<T: VertexLayout, B: RenderBackend = GlBackend> {
+    buffer: Buffer<B>,
+    len: usize,
+    vertex: PhantomData<T>,
+}
+
+impl<T: VertexLayout, B: RenderBackend> VertexBuffer<T, B> {
+    pub fn new(backend: &B) -> Self {
+        VertexBuffer {
+            buffer: Buffer::new(backend),
+            len: 0,
+            vertex: PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.buffer.id
+    }
+
+    /// Number of vertices last uploaded.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Bind the buffer and replace its contents with `vertices`.
+    pub fn upload(&mut self, vertices: &[T]) {
+        let backend = &mut self.buffer.backend;
+        backend.bind_buffer(self.buffer.id);
+        backend.update_vertices_dynamic(T::as_floats(vertices));
+        self.len = vertices.len();
+    }
+
+    /// Like `upload`, for vertices that are drawn many times without
+    /// changing.
+    pub fn upload_static(&mut self, vertices: &[T]) {
+        let backend = &mut self.buffer.backend;
+        backend.bind_buffer(self.buffer.id);
+        backend.update_vertices_static(T::as_floats(vertices));
+        self.len = vertices.len();
+    }
+}
+
+/// An element buffer holding indices of type `I`.
+pub struct IndexBuffer<I: ElementIndex, B: RenderBackend = GlBackend> {
+    buffer: Buffer<B>,
+    len: usize,
+    index: PhantomData<I>,
+}
+
+impl<I: ElementIndex, B: RenderBackend> IndexBuffer<I, B> {
+    pub fn new(backend: &B) -> Self {
+        IndexBuffer {
+            buffer: Buffer::new(backend),
+            len: 0,
+            index: PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.buffer.id
+    }
+
+    /// Number of indices last uploaded.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Bind the buffer to the bound vertex array and replace its contents
+    /// with `indices`.
+    pub fn upload(&mut self, indices: &[I]) {
+        let backend = &mut self.buffer.backend;
+        backend.bind_element_buffer(self.buffer.id);
+        backend.update_indices_dynamic(I::indices(indices));
+        self.len = indices.len();
+    }
+}
+
+/// A vertex array object.
+pub struct VertexArray<B: RenderBackend = GlBackend> {
+    id: u32,
+    backend: B,
+}
+
+impl<B: RenderBackend> VertexArray<B> {
+    pub fn new(backend: &B) -> Self {
+        let mut backend = backend.clone();
+        let id = backend.create_vertex_array();
+        VertexArray { id, backend }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Bind the vertex array and point its attributes at `buffer`, laid
+    /// out as `T` describes.
+    pub fn set_layout<T: VertexLayout>(&mut self, buffer: &VertexBuffer<T, B>) {
+        self.set_attributes(buffer, 0, 0);
+    }
+
+    /// Like `set_layout`, for a buffer with one `T` per instance. `T`'s
+    /// attributes are placed from `first_location` on, after those of the
+    /// vertices.
+    pub fn set_instance_layout<T: VertexLayout>(
+        &mut self,
+        buffer: &VertexBuffer<T, B>,
+        first_location: u32,
+    ) {
+        self.set_attributes(buffer, first_location, 1);
+    }
+
+    fn set_attributes<T: VertexLayout>(
+        &mut self,
+        buffer: &VertexBuffer<T, B>,
+        first_location: u32,
+        divisor: u32,
+    ) {
+        let backend = &mut self.backend;
+        backend.bind_vertex_array(self.id);
+        backend.bind_buffer(buffer.id());
+        for attribute in T::ATTRIBUTES {
+            // Split wide attributes into vec4 locations.
+            let mut components = attribute.components;
+            let mut offset = attribute.offset;
+            let mut location = first_location + attribute.location;
+            while components > 0 {
+                let count = components.min(4);
+                backend.vertex_attrib_pointer_float(location, count, T::stride(), offset);
+                backend.vertex_attrib_divisor(location, divisor);
+                components -= count;
+                offset += count as usize * std::mem::size_of::<f32>();
+                location += 1;
+            }
+        }
+    }
+
+    /// Bind the vertex array and make `buffer` its element buffer.
+    pub fn set_index_buffer<I: ElementIndex>(&mut self, buffer: &IndexBuffer<I, B>) {
+        self.backend.bind_vertex_array(self.id);
+        self.backend.bind_element_buffer(buffer.id());
+    }
+}
+
+impl<B: RenderBackend> Drop for VertexArray<B> {
+    fn drop(&mut self) {
+        self.backend.delete_vertex_array(self.id);
+    }
+}
+
+/// A 2D texture with nearest filtering and repeat wrapping.
+pub struct Texture2D<B: RenderBackend = GlBackend> {
+    id: u32,
+    backend: B,
+}
+
+impl<B: RenderBackend> Texture2D<B> {
+    pub fn new(backend: &B) -> Self {
+        let mut backend = backend.clone();
+        let id = backend.create_texture();
+        Texture2D { id, backend }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Bind the texture and replace its image.
+    pub fn upload(&mut self, texture: &Texture) {
+        self.backend.bind_texture(self.id);
+        self.backend.tex_image_2d(texture);
+    }
+
+    /// `upload` and build the mip chain on the GPU. It samples like
+    /// `texture.mip_chain(MipFilter::Box, false)` with `ResizeFilter::Nearest`.
+    pub fn upload_with_mipmaps(&mut self, texture: &Texture) {
+        self.upload(texture);
+        self.backend.generate_mipmaps();
+    }
+}
+
+impl<B: RenderBackend> Drop for Texture2D<B> {
+    fn drop(&mut self) {
+        self.backend.delete_texture(self.id);
+    }
+}
+
+/// A framebuffer object; see `RenderTarget` for one with attachments.
+pub struct Framebuffer<B: RenderBackend = GlBackend> {
+    id: u32,
+    backend: B,
+}
+
+impl<B: RenderBackend> Framebuffer<B> {
+    pub fn new(backend: &B) -> Self {
+        let mut backend = backend.clone();
+        let id = backend.create_framebuffer();
+        Framebuffer { id, backend }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl<B: RenderBackend> Drop for Framebuffer<B> {
+    fn drop(&mut self) {
+        self.backend.delete_framebuffer(self.id);
+    }
+}
+
+/// The printable ASCII range of a TrueType font baked into an `R8` atlas.
+pub struct Font {
+    data: c::FontData,
+    pixel_height: f32,
+    atlas_width: i32,
+    atlas_height: i32,
+}
+
+impl Font {
+    pub fn load(
+        ttf_path: &str,
+        pixel_height: f32,
+        atlas_width: i32,
+        atlas_height: i32,
+    ) -> Result<Self, EngineError> {
+        let data = create_font_data(ttf_path, pixel_height, atlas_width, atlas_height)?;
+        Ok(Font {
+            data,
+            pixel_height,
+            atlas_width,
+            atlas_height,
+        })
+    }
+
+    pub fn pixel_height(&self) -> f32 {
+        self.pixel_height
+    }
+
+    /// Copy of the atlas bitmap.
+    pub fn atlas(&self) -> Texture {
+        let mut atlas = Texture::new(self.atlas_width, self.atlas_height, PixelFormat::R8);
+        atlas.data.copy_from_slice(self.atlas_bitmap());
+        atlas
+    }
+
+    pub(crate) fn atlas_bitmap(&self) -> &[u8] {
+        let len = (self.atlas_width * self.atlas_height) as usize;
+        unsafe { std::slice::from_raw_parts(self.data.atlas_bitmap, len) }
+    }
+
+    pub(crate) fn atlas_bitmap_mut(&mut self) -> &mut [u8] {
+        let len = (self.atlas_width * self.atlas_height) as usize;
+        unsafe { std::slice::from_raw_parts_mut(self.data.atlas_bitmap, len) }
+    }
+
+    pub(crate) fn baked(&self, c: char) -> Option<&c::BakedChar> {
+        get_baked(&self.data, c)
+    }
+}
+
+impl Drop for Font {
+    fn drop(&mut self) {
+        unsafe {
+            c::free_font_data(c::FontData {
+                atlas_bitmap: self.data.atlas_bitmap,
+                baked_chars: self.data.baked_chars,
+            });
+        }
+    }
+}
@@ -1,47 +1,161 @@
+use crate::backend::{IndexType, Indices};
 use crate::c;
-use math::{vec3::*, *};
-use std::ffi::CString;
+use crate::error::*;
+use math::{
+    texture::{mip::Wrap, resample::ResizeFilter, *},
+    *,
+};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
 
-pub fn update_vertices_static(vertices: &Vec<f32>) {
+pub fn update_vertices_static(vertices: &[f32]) {
     let ptr: *const u8 = vertices.as_ptr() as *const u8;
-    let size_in_bytes = vertices.len() * std::mem::size_of::<f32>();
+    let size_in_bytes = std::mem::size_of_val(vertices);
     unsafe {
         c::update_vertices_static(ptr, size_in_bytes as u32);
     }
 }
 
-pub fn update_vertices_dynamic(vertices: &Vec<f32>) {
+pub fn update_vertices_dynamic(vertices: &[f32]) {
     let ptr: *const u8 = vertices.as_ptr() as *const u8;
-    let size_in_bytes = vertices.len() * std::mem::size_of::<f32>();
+    let size_in_bytes = std::mem::size_of_val(vertices);
     unsafe {
         c::update_vertices_dynamic(ptr, size_in_bytes as u32);
     }
 }
 
+pub fn update_indices_dynamic(indices: Indices) {
+    let (ptr, size_in_bytes) = match indices {
+        Indices::U16(i) => (i.as_ptr() as *const u8, std::mem::size_of_val(i)),
+        Indices::U32(i) => (i.as_ptr() as *const u8, std::mem::size_of_val(i)),
+    };
+    unsafe {
+        c::update_indices_dynamic(ptr, size_in_bytes as u32);
+    }
+}
+
+/// GL type of `kind` indices.
+pub fn gl_index_type(kind: IndexType) -> u32 {
+    match kind {
+        IndexType::U16 => 0x1403,
+        IndexType::U32 => 0x1405,
+    }
+}
+
+/// GL (internal format, format, type) used to upload a texture of `format`.
+/// `c::tex_image_2d` swizzles `R8` to luminance and `RG8` to luminance +
+/// alpha when sampled, as on the CPU.
+pub fn gl_pixel_format(format: PixelFormat) -> (i32, u32, u32) {
+    const UNSIGNED_BYTE: u32 = 0x1401;
+    const HALF_FLOAT: u32 = 0x140B;
+    const FLOAT: u32 = 0x1406;
+    const RED: u32 = 0x1903;
+    const RG: u32 = 0x8227;
+    const RGB: u32 = 0x1907;
+    const RGBA: u32 = 0x1908;
+    const DEPTH_COMPONENT: u32 = 0x1902;
+    match format {
+        PixelFormat::R8 => (0x8229, RED, UNSIGNED_BYTE),
+        PixelFormat::RG8 => (0x822B, RG, UNSIGNED_BYTE),
+        PixelFormat::RGB8 => (0x8051, RGB, UNSIGNED_BYTE),
+        PixelFormat::RGBA8 => (0x8058, RGBA, UNSIGNED_BYTE),
+        PixelFormat::R16F => (0x822D, RED, HALF_FLOAT),
+        PixelFormat::RGBA32F => (0x8814, RGBA, FLOAT),
+        PixelFormat::Depth => (0x8CAC, DEPTH_COMPONENT, FLOAT),
+    }
+}
+
+/// Upload `texture` to the currently bound 2D texture. A texture whose
+/// size is negative or whose `data` does not hold exactly `width * height`
+/// pixels, which GL would read past, prints a warning and is skipped.
+pub fn tex_image_2d(texture: &Texture) {
+    let size = texture.width.max(0) as usize
+        * texture.height.max(0) as usize
+        * texture.format.bytes_per_pixel();
+    if texture.width < 0 || texture.height < 0 || texture.data.len() != size {
+        eprintln!(
+            "warning: skipping upload of a {}x{} {:?} texture with {} bytes of data",
+            texture.width,
+            texture.height,
+            texture.format,
+            texture.data.len()
+        );
+        return;
+    }
+    let (internal_format, format, kind) = gl_pixel_format(texture.format);
+    unsafe {
+        c::tex_image_2d(
+            texture.data.as_ptr(),
+            texture.width,
+            texture.height,
+            internal_format,
+            format,
+            kind,
+        );
+    }
+}
+
+/// Sample `texture` on the CPU the way `c::create_texture` configures it
+/// on the GPU: nearest filtering and repeat wrapping. An empty texture
+/// reads as opaque black, like an incomplete GL texture.
+pub fn sample_texture(texture: &Texture, uv: Vec2) -> Color {
+    if texture.width <= 0 || texture.height <= 0 {
+        return Color::black();
+    }
+    texture.sample(uv, ResizeFilter::Nearest, Wrap::Repeat)
+}
+
 pub fn get_cstring(str: &str) -> CString {
     return CString::new(str).unwrap();
 }
 
-pub fn set_matrix4(program: u32, name: &str, ptr: *const f32) {
-    let a = get_cstring(name);
+/// Take ownership of a string allocated by cengine.
+fn take_c_string(ptr: *mut c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
     unsafe {
-        c::set_matrix4(program, a.as_ptr(), ptr);
+        let s = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+        c::free_string(ptr);
+        s
     }
 }
 
-pub fn set_vector3(program: u32, name: &str, v: Vec3) {
-    let a = get_cstring(name);
-    unsafe {
-        c::set_vector3(program, a.as_ptr(), v.x, v.y, v.z);
+/// Description of the last GLFW error.
+pub fn last_error() -> String {
+    unsafe { CStr::from_ptr(c::get_last_error()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Map an `initialize`/`initialize_headless` status to a result.
+pub fn context_result(status: i32) -> Result<(), EngineError> {
+    match status {
+        c::ENGINE_OK => Ok(()),
+        c::ENGINE_GLFW_INIT_FAILED => Err(EngineError::GlfwInit(last_error())),
+        c::ENGINE_GL_LOAD_FAILED => Err(EngineError::GlLoad),
+        c::ENGINE_FRAMEBUFFER_INCOMPLETE => Err(EngineError::FramebufferIncomplete),
+        c::ENGINE_WINDOW_FAILED => Err(EngineError::Context(last_error())),
+        _ => Err(EngineError::Context(format!("unknown status {}", status))),
     }
 }
 
-pub fn initialize_program(vertex_source: &str, fragment_source: &str) -> u32 {
+pub fn initialize_program(vertex_source: &str, fragment_source: &str) -> Result<u32, EngineError> {
     let a = get_cstring(vertex_source);
     let b = get_cstring(fragment_source);
-    unsafe {
-        return c::initialize_program(a.as_ptr(), b.as_ptr());
-    }
+    let result = unsafe { c::initialize_program(a.as_ptr(), b.as_ptr()) };
+    let log = take_c_string(result.info_log);
+    let stage = match result.failed_stage {
+        c::STAGE_NONE => return Ok(result.program),
+        c::STAGE_VERTEX => ShaderStage::Vertex,
+        c::STAGE_FRAGMENT => ShaderStage::Fragment,
+        _ => ShaderStage::Link,
+    };
+    Err(EngineError::Shader {
+        stage,
+        line: error_line(&log),
+        log,
+    })
 }
 
 pub fn get_baked(fontdata: &c::FontData, c: char) -> Option<&c::BakedChar> {
@@ -60,27 +174,34 @@ pub fn create_font_data(
     pixel_height: f32,
     atlas_width: i32,
     atlas_height: i32,
-) -> c::FontData {
+) -> Result<c::FontData, EngineError> {
     let a = get_cstring(ttf_path);
-    unsafe {
-        return c::create_font_data(a.as_ptr(), pixel_height, atlas_width, atlas_height);
+    let mut fontdata = c::FontData {
+        atlas_bitmap: std::ptr::null_mut(),
+        baked_chars: std::ptr::null_mut(),
+    };
+    let mut chars_baked = 0;
+    let status = unsafe {
+        c::create_font_data(
+            a.as_ptr(),
+            pixel_height,
+            atlas_width,
+            atlas_height,
+            &mut fontdata,
+            &mut chars_baked,
+        )
+    };
+    let path = ttf_path.to_string();
+    match status {
+        c::ENGINE_OK => Ok(fontdata),
+        c::ENGINE_FONT_NOT_FOUND => Err(EngineError::FontNotFound(path)),
+        c::ENGINE_FONT_INVALID => Err(EngineError::InvalidFont(path)),
+        c::ENGINE_FONT_ATLAS_TOO_SMALL => Err(EngineError::FontAtlasTooSmall {
+            path,
+            width: atlas_width,
+            height: atlas_height,
+            chars_baked,
+        }),
+        _ => Err(EngineError::InvalidFont(path)),
     }
 }
-
-pub fn add_vector2(vertices: &mut Vec<f32>, v: Vec2) {
-    vertices.push(v.x);
-    vertices.push(v.y);
-}
-
-pub fn add_vector3(vertices: &mut Vec<f32>, v: Vec3) {
-    vertices.push(v.x);
-    vertices.push(v.y);
-    vertices.push(v.z);
-}
-
-pub fn add_color(vertices: &mut Vec<f32>, c: Color) {
-    vertices.push(c.r);
-    vertices.push(c.g);
-    vertices.push(c.b);
-    vertices.push(c.a);
-}
@@ -1,12 +1,25 @@
 use std::char;
 
-use math::*;
+use math::{
+    texture::{PixelFormat, Texture},
+    *,
+};
+pub mod backend;
 mod c;
+pub mod error;
+pub mod gpu_mesh;
+pub mod handles;
 mod helper_functions;
 pub mod lit_renderer;
+pub mod render_target;
 pub mod simple_mesh;
 pub mod text_renderer;
 pub mod texture_renderer;
+pub mod uniform;
+pub mod vertex;
+
+pub use error::{EngineError, ShaderStage};
+pub use uniform::Uniform;
 
 #[repr(i32)]
 pub enum BufferBits {
@@ -213,9 +226,41 @@ pub fn window_should_close() -> bool {
     }
 }
 
-pub fn initialize(screen_width: i32, screen_height: i32) {
+pub fn initialize(screen_width: i32, screen_height: i32) -> Result<(), EngineError> {
+    helper_functions::context_result(unsafe { c::initialize(screen_width, screen_height) })
+}
+
+/// Create an offscreen context instead of a window, for rendering on
+/// machines without a display or GPU. Fails with `EngineError::Context`
+/// when neither an OSMesa nor a surfaceless EGL context is available.
+pub fn initialize_headless(width: i32, height: i32) -> Result<(), EngineError> {
+    helper_functions::context_result(unsafe { c::initialize_headless(width, height) })
+}
+
+/// Read back the bound framebuffer, the window or a `RenderTarget`, as
+/// `RGBA8`, with row 0 at the top.
+pub fn read_pixels() -> Texture {
+    let size = unsafe { c::get_target_size() };
+    let mut texture = Texture::new(size.x.max(0), size.y.max(0), PixelFormat::RGBA8);
+    unsafe {
+        c::read_pixels(
+            0,
+            0,
+            texture.width,
+            texture.height,
+            texture.data.as_mut_ptr(),
+        );
+    }
+    // GL returns the bottom row first.
+    texture.flip_v();
+    texture
+}
+
+/// Destroy the window or headless context. GPU handles dropped afterwards
+/// release nothing, so drop renderers first to free their resources.
+pub fn terminate() {
     unsafe {
-        c::initialize(screen_width, screen_height);
+        c::terminate();
     }
 }
 
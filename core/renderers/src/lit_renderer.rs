@@ -1,37 +1,80 @@
-use crate::c;
+use crate::backend::*;
+use crate::error::EngineError;
+use crate::gpu_mesh::*;
+use crate::handles::*;
 use crate::helper_functions::*;
 use crate::simple_mesh::SimpleMesh;
-use math::{mat4::*, texture::*, vec3::*, *};
-
-pub struct LitRenderer {
-    vertices: Vec<f32>,
-    vertex_count: u32,
-    texture: u32,
-    vao: u32,
-    vbo: u32,
-    program: u32,
+use crate::vertex_layout;
+use math::{mat4::*, texture::raster::*, texture::*, vec3::*, *};
+
+vertex_layout! {
+    pub struct LitVertex {
+        pub position: Vec3,
+        pub normal: Vec3,
+        pub uv: Vec2,
+    }
+}
+
+vertex_layout! {
+    /// Per-instance data of `LitRenderer::draw_mesh_instanced`. `color`
+    /// tints the texture.
+    pub struct LitInstance {
+        pub model: Mat4,
+        pub color: Color,
+    }
+}
+
+/// First attribute location of `LitInstance`, after those of `LitVertex`.
+pub(crate) const INSTANCE_LOCATION: u32 = 3;
+
+pub struct LitRenderer<B: RenderBackend = GlBackend> {
+    backend: B,
+    vertices: Vec<LitVertex>,
+    texture: Texture2D<B>,
+    cpu_texture: Texture,
+    vao: VertexArray<B>,
+    vbo: VertexBuffer<LitVertex, B>,
+    program: Program<B>,
+    view: Mat4,
+    projection: Mat4,
+    view_pos: Vec3,
+    light_pos: Vec3,
+    light_color: Color,
 }
 
 impl LitRenderer {
-    pub fn new() -> Self {
+    pub fn new() -> Result<Self, EngineError> {
+        LitRenderer::with_backend(GlBackend)
+    }
+}
+
+impl<B: RenderBackend> LitRenderer<B> {
+    pub fn with_backend(backend: B) -> Result<Self, EngineError> {
         let vertex_source = "#version 330 core
         layout (location = 0) in vec3 aPos;
         layout (location = 1) in vec3 aNormal;
         layout (location = 2) in vec2 aUV;
+        layout (location = 3) in mat4 aModel;
+        layout (location = 7) in vec4 aColor;
 
         out vec3 FragPos;
         out vec3 Normal;
         out vec2 uv;
+        out vec4 tint;
 
         uniform mat4 model;
         uniform mat4 view;
         uniform mat4 projection;
+        uniform bool instanced;
 
         void main()
         {
+            // Instance matrices arrive row by row, hence the transpose.
+            mat4 m = instanced ? transpose(aModel) : model;
+            tint = instanced ? aColor : vec4(1.0);
             uv = aUV;
-            FragPos = vec3(model * vec4(aPos, 1.0));
-            Normal = mat3(transpose(inverse(model))) * aNormal;
+            FragPos = vec3(m * vec4(aPos, 1.0));
+            Normal = mat3(transpose(inverse(m))) * aNormal;
 
             gl_Position = projection * view * vec4(FragPos, 1.0);
         }";
@@ -42,6 +85,7 @@ impl LitRenderer {
         in vec3 Normal;
         in vec3 FragPos;
         in vec2 uv;
+        in vec4 tint;
 
         uniform sampler2D tex;
         uniform vec3 lightPos;
@@ -67,32 +111,53 @@ impl LitRenderer {
             float spec = pow(max(dot(viewDir, reflectDir), 0.0), 32);
             vec3 specular = specularStrength * spec * lightColor;
 
-            vec4 objectColor = texture(tex, uv);
+            vec4 objectColor = texture(tex, uv) * tint;
             vec4 result = vec4(ambient + diffuse + specular, 1.0) * objectColor;
             FragColor = result;
         } ";
-        unsafe {
-            let program = initialize_program(vertex_source, fragment_source);
-            let texture = c::create_texture();
-            let vao = c::create_vao();
-            let vbo = c::create_vbo();
-            c::bind_vao(vao);
-            c::bind_vbo(vbo);
-            c::vertex_attrib_pointer_float(0, 3, 8 * 4, 0);
-            c::vertex_attrib_pointer_float(1, 3, 8 * 4, 3 * 4);
-            c::vertex_attrib_pointer_float(2, 2, 8 * 4, 6 * 4);
-
-            return LitRenderer {
-                vertices: vec![],
-                vertex_count: 0,
-                texture,
-                vao,
-                vbo,
-                program,
-            };
-        }
+        let program = Program::new(&backend, vertex_source, fragment_source)?;
+        let texture = Texture2D::new(&backend);
+        let mut vao = VertexArray::new(&backend);
+        let vbo = VertexBuffer::new(&backend);
+        vao.set_layout(&vbo);
+        return Ok(LitRenderer {
+            backend,
+            vertices: vec![],
+            texture,
+            cpu_texture: Texture::new(0, 0, PixelFormat::RGBA8),
+            vao,
+            vbo,
+            program,
+            view: Mat4::IDENTITY,
+            projection: Mat4::IDENTITY,
+            view_pos: Vec3::new(0.0, 0.0, 0.0),
+            light_pos: Vec3::new(0.0, 0.0, 0.0),
+            light_color: Color::white(),
+        });
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// Camera used by `draw_mesh` and `draw_mesh_cpu`.
+    pub fn set_camera(&mut self, view: Mat4, projection: Mat4, view_pos: Vec3) {
+        self.view = view;
+        self.projection = projection;
+        self.view_pos = view_pos;
+    }
+
+    /// Point light used by `draw_mesh` and `draw_mesh_cpu`.
+    pub fn set_light(&mut self, light_pos: Vec3, light_color: Color) {
+        self.light_pos = light_pos;
+        self.light_color = light_color;
     }
 
+    /// Draw the queued triangles with `model` and clear the queue.
     pub fn render(
         &mut self,
         model: Mat4,
@@ -102,61 +167,162 @@ impl LitRenderer {
         light_pos: Vec3,
         light_color: Color,
     ) {
-        unsafe {
-            c::bind_program(self.program);
-            c::enable_depth_test();
-            c::bind_vao(self.vao);
-            c::bind_vbo(self.vbo);
-            update_vertices_dynamic(&self.vertices);
-            c::bind_texture(self.texture);
-            set_matrix4(self.program, "model", model.to_f32_ptr());
-            set_matrix4(self.program, "view", view.to_f32_ptr());
-            set_matrix4(self.program, "projection", projection.to_f32_ptr());
-            set_vector3(self.program, "viewPos", view_pos);
-            set_vector3(self.program, "lightPos", light_pos);
-            set_vector3(self.program, "lightColor", light_color.to_vec3());
-            c::draw_triangle_arrays(self.vertex_count);
+        self.set_camera(view, projection, view_pos);
+        self.set_light(light_pos, light_color);
+        self.bind(model, false);
+        self.backend.bind_vertex_array(self.vao.id());
+        self.vbo.upload(&self.vertices);
+        self.backend.draw_triangle_arrays(self.vbo.len() as u32);
+        self.clear_vertices();
+    }
+
+    /// Draw `mesh` with `model` and the current camera and light. Nothing
+    /// is uploaded, so a mesh can be drawn many times per frame.
+    pub fn draw_mesh(&mut self, mesh: &GpuMesh<B>, model: Mat4) {
+        self.bind(model, false);
+        self.backend.bind_vertex_array(mesh.vertex_array().id());
+        self.backend.draw_triangle_arrays(mesh.len() as u32);
+    }
+
+    /// Draw `mesh` once per instance in a single draw call, with the
+    /// current camera and light.
+    pub fn draw_mesh_instanced(&mut self, mesh: &mut GpuMesh<B>, instances: &[LitInstance]) {
+        if instances.is_empty() {
+            return;
         }
+        mesh.upload_instances(instances);
+        self.bind(Mat4::IDENTITY, true);
+        self.backend.bind_vertex_array(mesh.vertex_array().id());
+        self.backend.draw_arrays_instanced(
+            Primitive::Triangles,
+            0,
+            mesh.len() as u32,
+            instances.len() as u32,
+        );
     }
 
-    pub fn draw_triangle(&mut self, pos: Triangle3, normal: Triangle3, uv: Triangle2) {
-        let vertices = &mut self.vertices;
-        self.vertex_count += 3;
-        add_vector3(vertices, pos.a);
-        add_vector3(vertices, normal.a);
-        add_vector2(vertices, uv.a);
+    /// Bind the program and texture and set the uniforms for a draw.
+    fn bind(&mut self, model: Mat4, instanced: bool) {
+        let backend = &mut self.backend;
+        backend.bind_program(self.program.id());
+        backend.set_depth_test(true);
+        backend.bind_texture(self.texture.id());
+        let program = &mut self.program;
+        program.set_uniform("model", model);
+        program.set_uniform("view", self.view);
+        program.set_uniform("projection", self.projection);
+        program.set_uniform("viewPos", self.view_pos);
+        program.set_uniform("lightPos", self.light_pos);
+        program.set_uniform("lightColor", self.light_color.to_vec3());
+        program.set_uniform("instanced", instanced);
+    }
 
-        add_vector3(vertices, pos.b);
-        add_vector3(vertices, normal.b);
-        add_vector2(vertices, uv.b);
+    /// Execute the queued draws into `raster` on the CPU with the same
+    /// uniforms and lighting as `render`, and clear the queue.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_cpu(
+        &mut self,
+        raster: &mut Rasterizer,
+        model: Mat4,
+        view: Mat4,
+        projection: Mat4,
+        view_pos: Vec3,
+        light_pos: Vec3,
+        light_color: Color,
+    ) {
+        self.set_camera(view, projection, view_pos);
+        self.set_light(light_pos, light_color);
+        self.rasterize(raster, &self.vertices, model, Color::white());
+        self.clear_vertices();
+    }
 
-        add_vector3(vertices, pos.c);
-        add_vector3(vertices, normal.c);
-        add_vector2(vertices, uv.c);
+    /// `draw_mesh` on the CPU.
+    pub fn draw_mesh_cpu(&self, raster: &mut Rasterizer, mesh: &GpuMesh<B>, model: Mat4) {
+        self.rasterize(raster, mesh.vertices(), model, Color::white());
     }
 
-    pub fn draw_simple_mesh(&mut self, simple_mesh: &SimpleMesh, uv: Vec2) {
-        let uv_tri = Triangle2 {
-            a: uv.clone(),
-            b: uv.clone(),
-            c: uv.clone(),
-        };
-        for t in &simple_mesh.triangles {
-            let normal = t.normal();
-            let normal_tri = Triangle3 {
-                a: normal.clone(),
-                b: normal.clone(),
-                c: normal.clone(),
-            };
-            self.draw_triangle(t.clone(), normal_tri, uv_tri.clone());
+    /// `draw_mesh_instanced` on the CPU.
+    pub fn draw_mesh_instanced_cpu(
+        &self,
+        raster: &mut Rasterizer,
+        mesh: &GpuMesh<B>,
+        instances: &[LitInstance],
+    ) {
+        for instance in instances {
+            self.rasterize(raster, mesh.vertices(), instance.model, instance.color);
         }
     }
 
-    pub fn update_texture(&mut self, texture: &Texture) {
-        let ptr = texture.data.as_ptr();
-        unsafe {
-            c::bind_texture(self.texture);
-            c::tex_image_2d(ptr, texture.width, texture.height, texture.channels);
+    fn rasterize(&self, raster: &mut Rasterizer, vertices: &[LitVertex], model: Mat4, tint: Color) {
+        raster.depth_test = true;
+        let view_projection = self.projection * self.view;
+        let normal_matrix = model.normal_matrix();
+        let vertices: Vec<ClipVertex<8>> = vertices
+            .iter()
+            .map(|v| {
+                let frag_pos = model.transform_point3(v.position);
+                let normal = normal_matrix.transform_dir3(v.normal);
+                ClipVertex {
+                    position: view_projection.transform_point4(frag_pos),
+                    varyings: [
+                        frag_pos.x, frag_pos.y, frag_pos.z, normal.x, normal.y, normal.z, v.uv.x,
+                        v.uv.y,
+                    ],
+                }
+            })
+            .collect();
+        let texture = &self.cpu_texture;
+        let (view_pos, light_pos) = (self.view_pos, self.light_pos);
+        let light_color = self.light_color.to_vec3();
+        raster.draw_triangles(&vertices, |v| {
+            let frag_pos = Vec3::new(v[0], v[1], v[2]);
+            let ambient = 0.1 * light_color;
+
+            let norm = Vec3::new(v[3], v[4], v[5]).normalized();
+            let light_dir = (light_pos - frag_pos).normalized();
+            let diffuse = norm.dot(light_dir).max(0.0) * light_color;
+
+            let view_dir = (view_pos - frag_pos).normalized();
+            let reflect_dir = -light_dir - 2.0 * norm.dot(-light_dir) * norm;
+            let spec = view_dir.dot(reflect_dir).max(0.0).powi(32);
+            let specular = 0.5 * spec * light_color;
+
+            let light = ambient + diffuse + specular;
+            let object = sample_texture(texture, Vec2::new(v[6], v[7]));
+            Color::new(
+                light.x * object.r * tint.r,
+                light.y * object.g * tint.g,
+                light.z * object.b * tint.b,
+                object.a * tint.a,
+            )
+        });
+    }
+
+    pub fn clear_vertices(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn draw_triangle(&mut self, pos: Triangle3, normal: Triangle3, uv: Triangle2) {
+        for (position, normal, uv) in [
+            (pos.a, normal.a, uv.a),
+            (pos.b, normal.b, uv.b),
+            (pos.c, normal.c, uv.c),
+        ] {
+            self.vertices.push(LitVertex {
+                position,
+                normal,
+                uv,
+            });
         }
     }
+
+    pub fn draw_simple_mesh(&mut self, simple_mesh: &SimpleMesh, uv: Vec2) {
+        self.vertices.extend(simple_mesh_vertices(simple_mesh, uv));
+    }
+
+    pub fn update_texture(&mut self, texture: &Texture) {
+        self.backend.bind_texture(self.texture.id());
+        self.backend.tex_image_2d(texture);
+        self.cpu_texture = texture.clone();
+    }
 }
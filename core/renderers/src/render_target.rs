@@ -0,0 +1,163 @@
+//! Offscreen framebuffers to render into and then sample from, e.g. for
+//! minimaps, editor viewports or post-processing.
+
+use crate::backend::*;
+use crate::error::EngineError;
+use crate::handles::*;
+use math::{texture::*, *};
+
+/// Color attachments a `RenderTarget` can have, the minimum GL 3.3
+/// guarantees.
+pub const MAX_COLOR_ATTACHMENTS: usize = 8;
+
+/// A framebuffer with color textures and an optional depth texture, all
+/// of the same size.
+///
+/// Its textures are stored bottom row first, as GL renders them; draw them
+/// with `TextureRenderer::draw_render_target` to show them upright.
+pub struct RenderTarget<B: RenderBackend = GlBackend> {
+    backend: B,
+    framebuffer: Framebuffer<B>,
+    colors: Vec<(Texture2D<B>, PixelFormat)>,
+    depth: Option<Texture2D<B>>,
+    size: Vec2i,
+}
+
+impl RenderTarget {
+    pub fn new(
+        width: i32,
+        height: i32,
+        color_formats: &[PixelFormat],
+        depth: bool,
+    ) -> Result<Self, EngineError> {
+        RenderTarget::with_backend(GlBackend, width, height, color_formats, depth)
+    }
+}
+
+impl<B: RenderBackend> RenderTarget<B> {
+    /// A target with one color attachment per entry of `color_formats`, in
+    /// order, and a depth attachment if `depth` is set. Fails with
+    /// `EngineError::TooManyColorAttachments` above `MAX_COLOR_ATTACHMENTS`
+    /// and `EngineError::FramebufferIncomplete` if the driver rejects the
+    /// combination. The window stays bound.
+    pub fn with_backend(
+        mut backend: B,
+        width: i32,
+        height: i32,
+        color_formats: &[PixelFormat],
+        depth: bool,
+    ) -> Result<Self, EngineError> {
+        if color_formats.len() > MAX_COLOR_ATTACHMENTS {
+            return Err(EngineError::TooManyColorAttachments(color_formats.len()));
+        }
+        let size = Vec2i {
+            x: width.max(1),
+            y: height.max(1),
+        };
+        let framebuffer = Framebuffer::new(&backend);
+        let colors: Vec<_> = color_formats
+            .iter()
+            .map(|&format| (Texture2D::new(&backend), format))
+            .collect();
+        let depth = depth.then(|| Texture2D::new(&backend));
+
+        backend.bind_framebuffer(framebuffer.id(), size);
+        for (i, (texture, _)) in colors.iter().enumerate() {
+            backend.attach_texture(Attachment::Color(i as u32), texture.id());
+        }
+        if let Some(texture) = &depth {
+            backend.attach_texture(Attachment::Depth, texture.id());
+        }
+        backend.set_draw_buffers(colors.len() as u32);
+        let mut target = RenderTarget {
+            backend,
+            framebuffer,
+            colors,
+            depth,
+            size,
+        };
+        target.allocate();
+        let complete = target.backend.framebuffer_complete();
+        let window_size = target.backend.window_size();
+        target.backend.bind_framebuffer(0, window_size);
+        if !complete {
+            return Err(EngineError::FramebufferIncomplete);
+        }
+        Ok(target)
+    }
+
+    pub fn width(&self) -> i32 {
+        self.size.x
+    }
+
+    pub fn height(&self) -> i32 {
+        self.size.y
+    }
+
+    pub fn size(&self) -> Vec2i {
+        self.size
+    }
+
+    /// Color attachment `index`, for sampling after rendering.
+    pub fn color(&self, index: usize) -> Option<&Texture2D<B>> {
+        self.colors.get(index).map(|(texture, _)| texture)
+    }
+
+    pub fn depth(&self) -> Option<&Texture2D<B>> {
+        self.depth.as_ref()
+    }
+
+    /// Render into this target from now on, with the viewport covering it.
+    pub fn bind(&mut self) {
+        self.backend
+            .bind_framebuffer(self.framebuffer.id(), self.size);
+        self.backend.set_viewport(0, 0, self.size.x, self.size.y);
+    }
+
+    /// Render into the window again, with the viewport covering it.
+    pub fn unbind(&mut self) {
+        let window_size = self.backend.window_size();
+        self.backend.bind_framebuffer(0, window_size);
+        self.backend
+            .set_viewport(0, 0, window_size.x, window_size.y);
+    }
+
+    /// Reallocate the attachments at a new size, discarding their
+    /// contents. Sizes are clamped to at least 1×1.
+    pub fn resize(&mut self, width: i32, height: i32) {
+        let size = Vec2i {
+            x: width.max(1),
+            y: height.max(1),
+        };
+        if size != self.size {
+            self.size = size;
+            self.allocate();
+        }
+    }
+
+    /// Match the window's size, e.g. once per frame for a full-screen
+    /// post-processing target. A minimized window, which has no size, is
+    /// ignored. Returns whether the target was resized.
+    pub fn resize_to_window(&mut self) -> bool {
+        let window_size = self.backend.window_size();
+        if window_size.x <= 0 || window_size.y <= 0 || window_size == self.size {
+            return false;
+        }
+        self.resize(window_size.x, window_size.y);
+        true
+    }
+
+    /// Give every attachment empty storage of the current size.
+    fn allocate(&mut self) {
+        let (width, height) = (self.size.x, self.size.y);
+        let backend = &mut self.backend;
+        for (texture, format) in &self.colors {
+            backend.bind_texture(texture.id());
+            backend.tex_image_2d(&Texture::new(width, height, *format));
+        }
+        if let Some(texture) = &self.depth {
+            backend.bind_texture(texture.id());
+            backend.tex_image_2d(&Texture::new(width, height, PixelFormat::Depth));
+        }
+    }
+}
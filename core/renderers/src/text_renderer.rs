@@ -1,22 +1,51 @@
-use crate::c;
+use crate::backend::*;
+use crate::error::EngineError;
+use crate::handles::*;
 use crate::helper_functions::*;
-use math::{mat4::*, rect::*, *};
+use crate::vertex_layout;
+use math::{
+    mat4::*,
+    rect::*,
+    texture::{Texture, raster::*},
+    *,
+};
+
+vertex_layout! {
+    pub struct TextVertex {
+        pub position: Vec2,
+        pub uv: Vec2,
+        pub color: Color,
+    }
+}
 
-pub struct TextRenderer {
-    vertices: Vec<f32>,
-    vertex_count: u32,
-    fontdata: c::FontData,
+pub struct TextRenderer<B: RenderBackend = GlBackend> {
+    backend: B,
+    vertices: Vec<TextVertex>,
+    font: Font,
     atlas_size: i32,
-    texture: u32,
-    vao: u32,
-    vbo: u32,
-    program: u32,
+    texture: Texture2D<B>,
+    /// CPU copy of the atlas for `render_cpu`.
+    atlas: Texture,
+    vao: VertexArray<B>,
+    vbo: VertexBuffer<TextVertex, B>,
+    program: Program<B>,
     fontheight: f32,
 }
 
 impl TextRenderer {
-    pub fn new(ttf_path: &str, fontheight: f32, atlas_size: i32) -> Self {
-        let fontdata = create_font_data(ttf_path, fontheight, atlas_size, atlas_size);
+    pub fn new(ttf_path: &str, fontheight: f32, atlas_size: i32) -> Result<Self, EngineError> {
+        TextRenderer::with_backend(GlBackend, ttf_path, fontheight, atlas_size)
+    }
+}
+
+impl<B: RenderBackend> TextRenderer<B> {
+    pub fn with_backend(
+        mut backend: B,
+        ttf_path: &str,
+        fontheight: f32,
+        atlas_size: i32,
+    ) -> Result<Self, EngineError> {
+        let mut font = Font::load(ttf_path, fontheight, atlas_size, atlas_size)?;
 
         let vertex_source = "#version 330 core
         layout (location = 0) in vec2 aPos;
@@ -45,76 +74,94 @@ impl TextRenderer {
         {
             FragColor = texture(tex, uv).r * color;
         }";
-        unsafe {
-            let program = initialize_program(vertex_source, fragment_source);
-            *fontdata
-                .atlas_bitmap
-                .add((atlas_size * atlas_size - 1) as usize) = 255u8;
-            let texture = c::create_texture();
-            c::tex_image_2d(fontdata.atlas_bitmap, atlas_size, atlas_size, 1);
-            let vao = c::create_vao();
-            let vbo = c::create_vbo();
-            c::bind_vao(vao);
-            c::bind_vbo(vbo);
-            c::vertex_attrib_pointer_float(0, 2, 8 * 4, 0);
-            c::vertex_attrib_pointer_float(1, 2, 8 * 4, 2 * 4);
-            c::vertex_attrib_pointer_float(2, 4, 8 * 4, 4 * 4);
-            return TextRenderer {
-                vertices: vec![],
-                vertex_count: 0,
-                fontdata,
-                atlas_size,
-                texture,
-                vao,
-                vbo,
-                program,
-                fontheight: fontheight,
-            };
+        let program = Program::new(&backend, vertex_source, fragment_source)?;
+        // Solid texel in the last corner, used by untextured shapes.
+        if let Some(last) = font.atlas_bitmap_mut().last_mut() {
+            *last = 255;
         }
+        let atlas = font.atlas();
+        let texture = Texture2D::new(&backend);
+        backend.bind_texture(texture.id());
+        backend.tex_image_2d(&atlas);
+        let mut vao = VertexArray::new(&backend);
+        let vbo = VertexBuffer::new(&backend);
+        vao.set_layout(&vbo);
+        return Ok(TextRenderer {
+            backend,
+            vertices: vec![],
+            font,
+            atlas_size,
+            texture,
+            atlas,
+            vao,
+            vbo,
+            program,
+            fontheight: fontheight,
+        });
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
     }
 
     pub fn render(&mut self) {
-        unsafe {
-            c::bind_program(self.program);
-            c::disable_depth_test();
-            c::bind_vao(self.vao);
-            c::bind_vbo(self.vbo);
-            update_vertices_dynamic(&self.vertices);
-            c::bind_texture(self.texture);
-            let window_size = c::get_window_size();
-            let view = Mat4::ortho(
-                0.0,
-                window_size.x as f32,
-                window_size.y as f32,
-                0.0,
-                -1.0,
-                1.0,
-            );
-            set_matrix4(self.program, "view", view.to_f32_ptr());
-            c::draw_triangle_arrays(self.vertex_count);
-            self.clear_vertices();
-        }
+        let backend = &mut self.backend;
+        backend.bind_program(self.program.id());
+        backend.set_depth_test(false);
+        backend.bind_vertex_array(self.vao.id());
+        self.vbo.upload(&self.vertices);
+        backend.bind_texture(self.texture.id());
+        let size = backend.target_size();
+        let view = Mat4::ortho(0.0, size.x as f32, size.y as f32, 0.0, -1.0, 1.0);
+        self.program.set_uniform("view", view);
+        backend.draw_triangle_arrays(self.vbo.len() as u32);
+        self.clear_vertices();
+    }
+
+    /// Execute the queued draws into `raster` on the CPU, as `render` does
+    /// on the GPU, with the view spanning the raster target.
+    pub fn render_cpu(&mut self, raster: &mut Rasterizer) {
+        raster.depth_test = false;
+        let view = Mat4::ortho(
+            0.0,
+            raster.width() as f32,
+            raster.height() as f32,
+            0.0,
+            -1.0,
+            1.0,
+        );
+        let vertices: Vec<ClipVertex<6>> = self
+            .vertices
+            .iter()
+            .map(|v| ClipVertex {
+                position: view.transform_point4(v.position.to_vec3()),
+                varyings: [v.uv.x, v.uv.y, v.color.r, v.color.g, v.color.b, v.color.a],
+            })
+            .collect();
+        let atlas = &self.atlas;
+        raster.draw_triangles(&vertices, |v| {
+            let r = sample_texture(atlas, Vec2::new(v[0], v[1])).r;
+            Color::new(r * v[2], r * v[3], r * v[4], r * v[5])
+        });
+        self.clear_vertices();
     }
 
     pub fn clear_vertices(&mut self) {
         self.vertices.clear();
-        self.vertex_count = 0;
     }
 
     fn draw_uv_triangle(&mut self, pos: Triangle2, uv: Triangle2, color: Color) {
-        let vertices = &mut self.vertices;
-        self.vertex_count += 3;
-        add_vector2(vertices, pos.a);
-        add_vector2(vertices, uv.a);
-        add_color(vertices, color);
-
-        add_vector2(vertices, pos.c);
-        add_vector2(vertices, uv.c);
-        add_color(vertices, color);
-
-        add_vector2(vertices, pos.b);
-        add_vector2(vertices, uv.b);
-        add_color(vertices, color);
+        for (position, uv) in [(pos.a, uv.a), (pos.c, uv.c), (pos.b, uv.b)] {
+            self.vertices.push(TextVertex {
+                position,
+                uv,
+                color,
+            });
+        }
     }
 
     pub fn draw_triangle(&mut self, pos: Triangle2, color: Color) {
@@ -152,7 +199,7 @@ impl TextRenderer {
     }
 
     pub fn draw_char(&mut self, x: f32, y: f32, c: char, fontheight: f32, color: Color) -> f32 {
-        let fontdata = get_baked(&self.fontdata, c);
+        let fontdata = self.font.baked(c);
         return match fontdata {
             Some(baked) => {
                 let fontscale = fontheight / self.fontheight;
@@ -187,7 +234,7 @@ impl TextRenderer {
     }
 
     pub fn measure_char(&mut self, c: char, fontheight: f32) -> f32 {
-        return match get_baked(&self.fontdata, c) {
+        return match self.font.baked(c) {
             Some(baked) => baked.xadvance * fontheight / self.fontheight,
             None => 0.0,
         };
@@ -1,18 +1,35 @@
-use crate::c;
+use crate::backend::*;
+use crate::error::EngineError;
+use crate::handles::*;
 use crate::helper_functions::*;
-use math::{mat4::*, rect::*, texture::*, *};
-
-pub struct TextureRenderer {
-    vertices: Vec<f32>,
-    vertex_count: u32,
-    texture: u32,
-    vao: u32,
-    vbo: u32,
-    program: u32,
+use crate::vertex_layout;
+use math::{mat4::*, rect::*, texture::raster::*, texture::*, *};
+
+vertex_layout! {
+    pub struct TextureVertex {
+        pub position: Vec2,
+        pub uv: Vec2,
+    }
+}
+
+pub struct TextureRenderer<B: RenderBackend = GlBackend> {
+    backend: B,
+    vertices: Vec<TextureVertex>,
+    texture: Texture2D<B>,
+    cpu_texture: Texture,
+    vao: VertexArray<B>,
+    vbo: VertexBuffer<TextureVertex, B>,
+    program: Program<B>,
 }
 
 impl TextureRenderer {
-    pub fn new() -> Self {
+    pub fn new() -> Result<Self, EngineError> {
+        TextureRenderer::with_backend(GlBackend)
+    }
+}
+
+impl<B: RenderBackend> TextureRenderer<B> {
+    pub fn with_backend(backend: B) -> Result<Self, EngineError> {
         let vertex_source = "#version 330 core
         layout (location = 0) in vec2 aPos;
         layout (location = 1) in vec2 aUV;
@@ -36,78 +53,95 @@ impl TextureRenderer {
         {
             FragColor = texture(tex, uv);
         }";
-        unsafe {
-            let program = initialize_program(vertex_source, fragment_source);
-            let texture = c::create_texture();
-            let vao = c::create_vao();
-            let vbo = c::create_vbo();
-            c::bind_vao(vao);
-            c::bind_vbo(vbo);
-            c::vertex_attrib_pointer_float(0, 2, 4 * 4, 0);
-            c::vertex_attrib_pointer_float(1, 2, 4 * 4, 2 * 4);
-            return TextureRenderer {
-                vertices: vec![],
-                vertex_count: 0,
-                texture,
-                vao,
-                vbo,
-                program,
-            };
-        }
+        let program = Program::new(&backend, vertex_source, fragment_source)?;
+        let texture = Texture2D::new(&backend);
+        let mut vao = VertexArray::new(&backend);
+        let vbo = VertexBuffer::new(&backend);
+        vao.set_layout(&vbo);
+        return Ok(TextureRenderer {
+            backend,
+            vertices: vec![],
+            texture,
+            cpu_texture: Texture::new(0, 0, PixelFormat::RGBA8),
+            vao,
+            vbo,
+            program,
+        });
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
     }
 
     pub fn render(&mut self) {
-        unsafe {
-            c::bind_program(self.program);
-            c::disable_depth_test();
-            c::bind_vao(self.vao);
-            c::bind_vbo(self.vbo);
-            update_vertices_dynamic(&self.vertices);
-            c::bind_texture(self.texture);
-            let window_size = c::get_window_size();
-            let view = Mat4::ortho(
-                0.0,
-                window_size.x as f32,
-                window_size.y as f32,
-                0.0,
-                -1.0,
-                1.0,
-            );
-            set_matrix4(self.program, "view", view.to_f32_ptr());
-            c::draw_triangle_arrays(self.vertex_count);
-            self.clear_vertices();
-        }
+        self.render_texture(self.texture.id());
+    }
+
+    /// Render the queued quads sampling `texture` instead of the texture
+    /// set with `update_texture`, e.g. a `RenderTarget` attachment.
+    pub fn render_with(&mut self, texture: &Texture2D<B>) {
+        self.render_texture(texture.id());
+    }
+
+    fn render_texture(&mut self, texture: u32) {
+        let backend = &mut self.backend;
+        backend.bind_program(self.program.id());
+        backend.set_depth_test(false);
+        backend.bind_vertex_array(self.vao.id());
+        self.vbo.upload(&self.vertices);
+        backend.bind_texture(texture);
+        let size = backend.target_size();
+        let view = Mat4::ortho(0.0, size.x as f32, size.y as f32, 0.0, -1.0, 1.0);
+        self.program.set_uniform("view", view);
+        backend.draw_triangle_arrays(self.vbo.len() as u32);
+        self.clear_vertices();
+    }
+
+    /// Execute the queued draws into `raster` on the CPU, as `render` does
+    /// on the GPU, with the view spanning the raster target.
+    pub fn render_cpu(&mut self, raster: &mut Rasterizer) {
+        raster.depth_test = false;
+        let view = Mat4::ortho(
+            0.0,
+            raster.width() as f32,
+            raster.height() as f32,
+            0.0,
+            -1.0,
+            1.0,
+        );
+        let vertices: Vec<ClipVertex<2>> = self
+            .vertices
+            .iter()
+            .map(|v| ClipVertex {
+                position: view.transform_point4(v.position.to_vec3()),
+                varyings: [v.uv.x, v.uv.y],
+            })
+            .collect();
+        let texture = &self.cpu_texture;
+        raster.draw_triangles(&vertices, |v| {
+            sample_texture(texture, Vec2::new(v[0], v[1]))
+        });
+        self.clear_vertices();
     }
 
     pub fn update_texture(&mut self, texture: &Texture) {
-        let ptr = texture.data.as_ptr();
-        unsafe {
-            c::bind_texture(self.texture);
-            c::tex_image_2d(ptr, texture.width, texture.height, texture.channels);
-        }
+        self.backend.bind_texture(self.texture.id());
+        self.backend.tex_image_2d(texture);
+        self.cpu_texture = texture.clone();
     }
 
     pub fn clear_vertices(&mut self) {
         self.vertices.clear();
-        self.vertex_count = 0;
     }
 
     pub fn draw_triangle(&mut self, t: &Triangle2, uv: &Triangle2) {
-        self.vertex_count += 3;
-        self.vertices.push(t.a.x);
-        self.vertices.push(t.a.y);
-        self.vertices.push(uv.a.x);
-        self.vertices.push(uv.a.y);
-
-        self.vertices.push(t.c.x);
-        self.vertices.push(t.c.y);
-        self.vertices.push(uv.c.x);
-        self.vertices.push(uv.c.y);
-
-        self.vertices.push(t.b.x);
-        self.vertices.push(t.b.y);
-        self.vertices.push(uv.b.x);
-        self.vertices.push(uv.b.y);
+        for (position, uv) in [(t.a, uv.a), (t.c, uv.c), (t.b, uv.b)] {
+            self.vertices.push(TextureVertex { position, uv });
+        }
     }
 
     pub fn draw_texture(&mut self, rect: &Rect, uv: &Rect) {
@@ -115,6 +149,19 @@ impl TextureRenderer {
         self.draw_triangle(&rect.tri2(), &uv.tri2());
     }
 
+    /// Queue `rect` showing a whole `RenderTarget` attachment upright;
+    /// render it with `render_with`.
+    pub fn draw_render_target(&mut self, rect: &Rect) {
+        // Render targets store their bottom row first.
+        let uv = Rect {
+            x: 0.0,
+            y: 1.0,
+            w: 1.0,
+            h: -1.0,
+        };
+        self.draw_texture(rect, &uv);
+    }
+
     pub fn draw_full_texture(&mut self, rect: &Rect) {
         let uv = Rect {
             x: 0.0,
@@ -0,0 +1,118 @@
+//! Values that can be assigned to shader uniforms.
+
+use math::{mat4::*, vec3::*, *};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Uniform {
+    Float(f32),
+    Int(i32),
+    Bool(bool),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4([f32; 4]),
+    /// Row-major 3×3 matrix.
+    Mat3([f32; 9]),
+    Mat4(Mat4),
+    FloatArray(Vec<f32>),
+    IntArray(Vec<i32>),
+    Vec2Array(Vec<Vec2>),
+    Vec3Array(Vec<Vec3>),
+    Vec4Array(Vec<[f32; 4]>),
+    Mat4Array(Vec<Mat4>),
+    /// Texture unit a sampler reads from; bind the texture to it with
+    /// `RenderBackend::bind_texture_unit`.
+    Sampler(u32),
+}
+
+impl Uniform {
+    /// The upper-left 3×3 of `m`, e.g. for a normal matrix.
+    pub fn mat3_from(m: Mat4) -> Uniform {
+        Uniform::Mat3([
+            m.m[0], m.m[1], m.m[2], m.m[4], m.m[5], m.m[6], m.m[8], m.m[9], m.m[10],
+        ])
+    }
+}
+
+impl From<f32> for Uniform {
+    fn from(v: f32) -> Self {
+        Uniform::Float(v)
+    }
+}
+
+impl From<i32> for Uniform {
+    fn from(v: i32) -> Self {
+        Uniform::Int(v)
+    }
+}
+
+impl From<bool> for Uniform {
+    fn from(v: bool) -> Self {
+        Uniform::Bool(v)
+    }
+}
+
+impl From<Vec2> for Uniform {
+    fn from(v: Vec2) -> Self {
+        Uniform::Vec2(v)
+    }
+}
+
+impl From<Vec3> for Uniform {
+    fn from(v: Vec3) -> Self {
+        Uniform::Vec3(v)
+    }
+}
+
+impl From<[f32; 4]> for Uniform {
+    fn from(v: [f32; 4]) -> Self {
+        Uniform::Vec4(v)
+    }
+}
+
+impl From<Color> for Uniform {
+    fn from(c: Color) -> Self {
+        Uniform::Vec4([c.r, c.g, c.b, c.a])
+    }
+}
+
+impl From<Mat4> for Uniform {
+    fn from(m: Mat4) -> Self {
+        Uniform::Mat4(m)
+    }
+}
+
+impl From<&[f32]> for Uniform {
+    fn from(v: &[f32]) -> Self {
+        Uniform::FloatArray(v.to_vec())
+    }
+}
+
+impl From<&[i32]> for Uniform {
+    fn from(v: &[i32]) -> Self {
+        Uniform::IntArray(v.to_vec())
+    }
+}
+
+impl From<&[Vec2]> for Uniform {
+    fn from(v: &[Vec2]) -> Self {
+        Uniform::Vec2Array(v.to_vec())
+    }
+}
+
+impl From<&[Vec3]> for Uniform {
+    fn from(v: &[Vec3]) -> Self {
+        Uniform::Vec3Array(v.to_vec())
+    }
+}
+
+impl From<&[[f32; 4]]> for Uniform {
+    fn from(v: &[[f32; 4]]) -> Self {
+        Uniform::Vec4Array(v.to_vec())
+    }
+}
+
+impl From<&[Mat4]> for Uniform {
+    fn from(v: &[Mat4]) -> Self {
+        Uniform::Mat4Array(v.to_vec())
+    }
+}
@@ -0,0 +1,128 @@
+//! Vertex formats described by the Rust struct that holds a vertex.
+//!
+//! `vertex_layout!` declares a `#[repr(C)]` struct and implements
+//! `VertexLayout` for it, assigning attribute locations in field order.
+//! A `VertexBuffer<T>` uploads slices of such structs and
+//! `VertexArray::set_layout` points the attributes at it, or
+//! `VertexArray::set_instance_layout` for per-instance data:
+//!
+//! ```ignore
+//! vertex_layout! {
+//!     pub struct ColorVertex {
+//!         pub position: Vec2, // location 0
+//!         pub color: Color,   // location 1
+//!     }
+//! }
+//! ```
+
+use math::{mat4::*, vec3::*, *};
+
+/// One float attribute of a vertex: `components` floats at byte `offset`.
+/// Attributes of more than 4 floats, like matrices, take one location per
+/// 4 floats, as a GLSL `mat4` input does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub components: i32,
+    pub offset: usize,
+}
+
+impl VertexAttribute {
+    /// Number of locations the attribute takes.
+    pub const fn locations(components: i32) -> u32 {
+        (components as u32).div_ceil(4)
+    }
+}
+
+/// A field type usable in a vertex.
+///
+/// # Safety
+///
+/// The type must consist of exactly `COMPONENTS` `f32` values and nothing
+/// else, so a vertex made of such fields can be viewed as `&[f32]`.
+pub unsafe trait VertexComponent: Copy {
+    const COMPONENTS: i32;
+}
+
+unsafe impl VertexComponent for f32 {
+    const COMPONENTS: i32 = 1;
+}
+
+unsafe impl VertexComponent for Vec2 {
+    const COMPONENTS: i32 = 2;
+}
+
+unsafe impl VertexComponent for Vec3 {
+    const COMPONENTS: i32 = 3;
+}
+
+unsafe impl VertexComponent for Color {
+    const COMPONENTS: i32 = 4;
+}
+
+/// Row-major, so each row is one vec4 location and a GLSL `mat4` input
+/// receives the transpose.
+unsafe impl VertexComponent for Mat4 {
+    const COMPONENTS: i32 = 16;
+}
+
+unsafe impl<const N: usize> VertexComponent for [f32; N] {
+    const COMPONENTS: i32 = N as i32;
+}
+
+/// A vertex struct and the attributes it feeds.
+///
+/// # Safety
+///
+/// The type must be `#[repr(C)]` with only `VertexComponent` fields, and
+/// `ATTRIBUTES` must lie within it. Implement it with `vertex_layout!`.
+pub unsafe trait VertexLayout: Copy {
+    const ATTRIBUTES: &'static [VertexAttribute];
+
+    /// Size of one vertex in bytes.
+    fn stride() -> u32 {
+        std::mem::size_of::<Self>() as u32
+    }
+
+    /// The vertices as the flat float array uploaded to the GPU.
+    fn as_floats(vertices: &[Self]) -> &[f32] {
+        let len = std::mem::size_of_val(vertices) / std::mem::size_of::<f32>();
+        unsafe { std::slice::from_raw_parts(vertices.as_ptr() as *const f32, len) }
+    }
+}
+
+/// Declare a vertex struct and implement `VertexLayout` for it. Attribute
+/// locations follow the field order, starting at 0; a `Mat4` field takes
+/// four.
+#[macro_export]
+macro_rules! vertex_layout {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($field_vis:vis $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[repr(C)]
+        #[derive(Clone, Copy, Debug)]
+        $vis struct $name {
+            $($field_vis $field: $ty),*
+        }
+
+        unsafe impl $crate::vertex::VertexLayout for $name {
+            const ATTRIBUTES: &'static [$crate::vertex::VertexAttribute] = {
+                let mut location = 0;
+                &[$({
+                    let components = <$ty as $crate::vertex::VertexComponent>::COMPONENTS;
+                    let locations = $crate::vertex::VertexAttribute::locations(components);
+                    location += locations;
+                    $crate::vertex::VertexAttribute {
+                        location: location - locations,
+                        components,
+                        offset: ::std::mem::offset_of!($name, $field),
+                    }
+                }),*]
+            };
+        }
+    };
+}
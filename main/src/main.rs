@@ -21,14 +21,24 @@ fn draw_equilateral_triangle(
 }
 
 fn main() {
-    initialize(2000, 1600);
+    if let Err(e) = initialize(2000, 1600) {
+        eprintln!("{}", e);
+        return;
+    }
     cull_back_faces();
     enable_transparency();
 
     let fontheight = 50.0;
     let lineheight = fontheight * 1.3;
     let indentsize = fontheight;
-    let mut text_renderer = TextRenderer::new("assets/JetBrainsMono-Medium.ttf", fontheight, 512);
+    let mut text_renderer =
+        match TextRenderer::new("assets/JetBrainsMono-Medium.ttf", fontheight, 512) {
+            Ok(text_renderer) => text_renderer,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        };
     let mut nodes = Nodes::new();
     let n1 = nodes.add_node(0, "node1");
     nodes.add_node(0, "node2");
@@ -73,4 +83,6 @@ fn main() {
 
         swap_buffers();
     }
+    drop(text_renderer);
+    terminate();
 }